@@ -0,0 +1,144 @@
+// Compares the typed decoding path against the previous `Vec<Vec<f32>>` one on
+// a dense, low-threshold span model output. Run with `cargo bench --bench decode`.
+use glinerrust::decoder::{BaseDecoder, Decoder, SelectionStrategy, SpanDecoder};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const BATCH_SIZE: usize = 4;
+const NUM_WORDS: usize = 256;
+const MAX_WIDTH: usize = 12;
+const NUM_ENTITIES: usize = 8;
+const THRESHOLD: f32 = 0.3;
+const ITERATIONS: u32 = 5;
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+// Decoding as it was before spans became `SpanCandidate`s: every logit goes
+// through the sigmoid and every overlap check allocates both offsets.
+fn legacy_decode(
+    words_start_idx: &[Vec<usize>],
+    words_end_idx: &[Vec<usize>],
+    model_output: &[f32],
+    flat_ner: bool,
+) -> Vec<Vec<Vec<f32>>> {
+    let mut spans: Vec<Vec<Vec<f32>>> = vec![vec![]; BATCH_SIZE];
+    for (id, &value) in model_output.iter().enumerate() {
+        let batch = id / (NUM_WORDS * MAX_WIDTH * NUM_ENTITIES);
+        let start_token = (id / (MAX_WIDTH * NUM_ENTITIES)) % NUM_WORDS;
+        let end_token = start_token + ((id / NUM_ENTITIES) % MAX_WIDTH);
+        let prob = sigmoid(value);
+        if prob >= THRESHOLD
+            && start_token < words_start_idx[batch].len()
+            && end_token < words_end_idx[batch].len()
+        {
+            spans[batch].push(vec![
+                words_start_idx[batch][start_token] as f32,
+                words_end_idx[batch][end_token] as f32,
+                (id % NUM_ENTITIES) as f32,
+                prob,
+            ]);
+        }
+    }
+
+    let has_ov = |idx1: &[usize], idx2: &[usize]| {
+        let overlapping = !(idx1[0] > idx2[1] || idx2[0] > idx1[1]);
+        let nested = (idx1[0] <= idx2[0] && idx1[1] >= idx2[1])
+            || (idx2[0] <= idx1[0] && idx2[1] >= idx1[1]);
+        idx1[0..2] == idx2[0..2] || (overlapping && (flat_ner || !nested))
+    };
+    spans
+        .into_iter()
+        .map(|mut spans| {
+            spans.sort_by(|a, b| b[3].partial_cmp(&a[3]).unwrap());
+            let mut new_list: Vec<Vec<f32>> = Vec::new();
+            for b in spans.iter() {
+                let flag = new_list.iter().any(|new_span| {
+                    has_ov(
+                        &b[0..2].iter().map(|&x| x as usize).collect::<Vec<usize>>(),
+                        &new_span[0..2]
+                            .iter()
+                            .map(|&x| x as usize)
+                            .collect::<Vec<usize>>(),
+                    )
+                });
+                if !flag {
+                    new_list.push(b.clone());
+                }
+            }
+            new_list.sort_by(|a, b| a[0].partial_cmp(&b[0]).unwrap());
+            new_list
+        })
+        .collect()
+}
+
+fn time(mut run: impl FnMut() -> usize) -> (Duration, usize) {
+    let mut kept = run();
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        kept = black_box(run());
+    }
+    (started.elapsed() / ITERATIONS, kept)
+}
+
+fn main() {
+    // Deterministic logits in [-4, 2), so that about half of the cells pass
+    // the threshold.
+    let mut state: u32 = 0x9e37_79b9;
+    let model_output: Vec<f32> = (0..BATCH_SIZE * NUM_WORDS * MAX_WIDTH * NUM_ENTITIES)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state as f32 / u32::MAX as f32) * 6.0 - 4.0
+        })
+        .collect();
+    // Words of 5 bytes separated by a space.
+    let words_start_idx: Vec<Vec<usize>> =
+        vec![(0..NUM_WORDS).map(|word| word * 6).collect(); BATCH_SIZE];
+    let words_end_idx: Vec<Vec<usize>> =
+        vec![(0..NUM_WORDS).map(|word| word * 6 + 5).collect(); BATCH_SIZE];
+    let decoder = SpanDecoder::new();
+    let thresholds = vec![THRESHOLD; NUM_ENTITIES];
+
+    for flat_ner in [true, false] {
+        let (legacy, legacy_kept) = time(|| {
+            legacy_decode(&words_start_idx, &words_end_idx, &model_output, flat_ner)
+                .iter()
+                .map(Vec::len)
+                .sum()
+        });
+        let (typed, typed_kept) = time(|| {
+            decoder
+                .decode_candidates(
+                    BATCH_SIZE,
+                    NUM_WORDS,
+                    MAX_WIDTH,
+                    NUM_ENTITIES,
+                    &words_start_idx,
+                    &words_end_idx,
+                    &model_output,
+                    &thresholds,
+                    &[],
+                )
+                .iter_mut()
+                .map(|spans| {
+                    decoder
+                        .select_spans(spans, flat_ner, false, SelectionStrategy::Greedy)
+                        .len()
+                })
+                .sum()
+        });
+        assert_eq!(legacy_kept, typed_kept);
+
+        println!(
+            "flat_ner={:<5} legacy {:>10.2?}  typed {:>10.2?}  speedup {:.1}x  ({} spans kept)",
+            flat_ner,
+            legacy,
+            typed,
+            legacy.as_secs_f64() / typed.as_secs_f64(),
+            typed_kept
+        );
+    }
+}
@@ -8,14 +8,15 @@ async fn main() -> Result<()> {
         max_width: Some(12),
         model_path: "./model_quantized.onnx".to_string(),
         num_threads: Some(4),
+        ..Default::default()
     });
 
-    let _ = gliner.initialize().await?;
+    gliner.initialize().await?;
 
     let input_texts = vec![
 
         "Cristiano Ronaldo dos Santos Aveiro (Portuguese pronunciation: [kɾiʃˈtjɐnu ʁɔˈnaldu]; born 5 February 1985) is a Portuguese professional footballer who plays as a forward for and captains both Saudi Pro League club Al Nassr and the Portugal national team.".to_owned(),
-      
+
     ];
 
     let entities = vec![
@@ -43,5 +44,3 @@ async fn main() -> Result<()> {
     }
     Ok(())
 }
-
-
@@ -0,0 +1,51 @@
+#[derive(Debug, Clone)]
+pub struct BatchingConfig {
+    // Maximum number of texts in one session run.
+    pub batch_size: usize,
+    // Optional cap on `texts in batch * longest text in subword tokens`, which
+    // bounds the size of the padded inputs independently of the number of
+    // texts. The label prompt, the same for every text, is not counted.
+    pub max_tokens: Option<usize>,
+}
+
+impl Default for BatchingConfig {
+    fn default() -> Self {
+        BatchingConfig {
+            batch_size: 8,
+            max_tokens: None,
+        }
+    }
+}
+
+// Groups text indices into micro-batches of texts with similar lengths, given
+// in subword tokens. Texts are sorted by length so that padding within a
+// batch stays small; texts without tokens are left out since they cannot
+// contain entities.
+pub fn plan_batches(token_counts: &[usize], config: &BatchingConfig) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..token_counts.len())
+        .filter(|&id| token_counts[id] > 0)
+        .collect();
+    order.sort_by_key(|&id| token_counts[id]);
+
+    let batch_size = config.batch_size.max(1);
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+
+    for id in order {
+        // Ids arrive in increasing length, so the new text is the longest one.
+        let padded = (current.len() + 1) * token_counts[id];
+        let fits = current.len() < batch_size
+            && config
+                .max_tokens
+                .is_none_or(|max_tokens| padded <= max_tokens);
+        if !fits && !current.is_empty() {
+            batches.push(std::mem::take(&mut current));
+        }
+        current.push(id);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    batches
+}
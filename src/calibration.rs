@@ -0,0 +1,243 @@
+use crate::error::{GlinerError, Result};
+use crate::types::InferenceResultSingle;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// Smallest scale a fit may end with. Calibration has to keep scores in the same
+// order for thresholds to be checked on logits before the sigmoid.
+const MIN_SCALE: f32 = 1e-3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalibrationMethod {
+    // Divides logits by a fitted temperature.
+    #[default]
+    Temperature,
+    // Fits both a scale and a bias of the logits.
+    Platt,
+}
+
+// Maps the logit `x` of a label to the calibrated probability
+// `sigmoid(scale * x + bias)`. A temperature `t` is a scale of `1 / t`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LabelCalibration {
+    pub scale: f32,
+    #[serde(default)]
+    pub bias: f32,
+}
+
+impl Default for LabelCalibration {
+    fn default() -> Self {
+        LabelCalibration {
+            scale: 1.0,
+            bias: 0.0,
+        }
+    }
+}
+
+impl LabelCalibration {
+    pub fn logit(&self, x: f32) -> f32 {
+        self.scale * x + self.bias
+    }
+
+    // Raw logit a cell needs for its calibrated logit to reach `threshold`.
+    pub fn raw_logit(&self, threshold: f32) -> f32 {
+        (threshold - self.bias) / self.scale
+    }
+}
+
+// Per-label calibration of a model, stored as `calibration.json` next to it.
+// Labels without an entry keep their raw scores.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calibration {
+    pub method: CalibrationMethod,
+    pub labels: HashMap<String, LabelCalibration>,
+}
+
+// One scored label of a span from a dev set, and whether the span carries that
+// label in the gold annotations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationSample {
+    pub label: String,
+    pub score: f32,
+    pub correct: bool,
+}
+
+impl Calibration {
+    // Fits the parameters of every label having both correct and incorrect
+    // samples by minimizing their log-loss. Samples must come from an
+    // uncalibrated model.
+    pub fn fit(samples: &[CalibrationSample], method: CalibrationMethod) -> Self {
+        let mut by_label: HashMap<&str, Vec<(f64, bool)>> = HashMap::new();
+        for sample in samples {
+            let score = (sample.score as f64).clamp(1e-6, 1.0 - 1e-6);
+            by_label
+                .entry(sample.label.as_str())
+                .or_default()
+                .push(((score / (1.0 - score)).ln(), sample.correct));
+        }
+
+        let labels = by_label
+            .into_iter()
+            .filter(|(_, points)| {
+                points.iter().any(|&(_, correct)| correct)
+                    && points.iter().any(|&(_, correct)| !correct)
+            })
+            .map(|(label, points)| (label.to_string(), fit_label(&points, method)))
+            .collect();
+
+        Calibration { method, labels }
+    }
+
+    pub fn label(&self, label: &str) -> LabelCalibration {
+        self.labels.get(label).copied().unwrap_or_default()
+    }
+
+    // Calibration of `entities`, indexed by class id.
+    pub fn for_entities(&self, entities: &[&str]) -> Vec<LabelCalibration> {
+        entities.iter().map(|ent| self.label(ent)).collect()
+    }
+
+    // Rejects labels whose scale would reverse or flatten the order of scores,
+    // which thresholds checked on raw logits rely on.
+    pub fn validate(&self) -> Result<()> {
+        for (label, calibration) in &self.labels {
+            let valid = calibration.scale >= MIN_SCALE
+                && calibration.scale.is_finite()
+                && calibration.bias.is_finite();
+            if !valid {
+                return Err(GlinerError::InitializationError(format!(
+                    "Calibration of {} needs a finite scale of at least {} and a finite bias, got {} and {}",
+                    label, MIN_SCALE, calibration.scale, calibration.bias
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content =
+            std::fs::read_to_string(path).map_err(|e| GlinerError::file("reading", path, e))?;
+        let calibration: Calibration =
+            serde_json::from_str(&content).map_err(|e| GlinerError::file("parsing", path, e))?;
+        calibration.validate()?;
+        Ok(calibration)
+    }
+
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| GlinerError::file("writing", path, e))?;
+        std::fs::write(path, content).map_err(|e| GlinerError::file("writing", path, e))
+    }
+}
+
+// Turns predictions on a dev set into calibration samples by matching them
+// against `gold` `(start, end, label)` entities of each text. Every entry of
+// `label_scores` is used when predictions were made with `top_k`, which also
+// gives samples for the labels a span does not have. Predictions should be made
+// with a low threshold so that incorrect spans are represented too.
+pub fn calibration_samples(
+    predictions: &[InferenceResultSingle],
+    gold: &[Vec<(usize, usize, String)>],
+) -> Vec<CalibrationSample> {
+    let mut samples = Vec::new();
+    for (prediction, gold) in predictions.iter().zip(gold) {
+        let gold: HashSet<(usize, usize, &str)> = gold
+            .iter()
+            .map(|(start, end, label)| (*start, *end, label.as_str()))
+            .collect();
+        let mut seen: HashSet<(usize, usize, &str)> = HashSet::new();
+        for entity in &prediction.entities {
+            let scores = std::iter::once((entity.label.as_str(), entity.score)).chain(
+                entity
+                    .label_scores
+                    .iter()
+                    .map(|label_score| (label_score.label.as_str(), label_score.score)),
+            );
+            for (label, score) in scores {
+                if seen.insert((entity.start, entity.end, label)) {
+                    samples.push(CalibrationSample {
+                        label: label.to_string(),
+                        score,
+                        correct: gold.contains(&(entity.start, entity.end, label)),
+                    });
+                }
+            }
+        }
+    }
+    samples
+}
+
+// Newton's method on the log-loss of `sigmoid(scale * x + bias)`, with the
+// smoothed targets of Platt scaling so that separable samples still have a
+// finite optimum. Steps are halved until they lower the loss.
+fn fit_label(points: &[(f64, bool)], method: CalibrationMethod) -> LabelCalibration {
+    let positives = points.iter().filter(|&&(_, correct)| correct).count() as f64;
+    let negatives = points.len() as f64 - positives;
+    let high = (positives + 1.0) / (positives + 2.0);
+    let low = 1.0 / (negatives + 2.0);
+    let target = |correct: bool| if correct { high } else { low };
+    let loss = |scale: f64, bias: f64| -> f64 {
+        points
+            .iter()
+            .map(|&(x, correct)| {
+                let z = scale * x + bias;
+                // log(1 + e^z) - t * z, computed without overflow.
+                z.max(0.0) + (-z.abs()).exp().ln_1p() - target(correct) * z
+            })
+            .sum()
+    };
+
+    let (mut scale, mut bias) = (1.0f64, 0.0f64);
+    let mut current = loss(scale, bias);
+    for _ in 0..100 {
+        let (mut g_scale, mut g_bias) = (0.0, 0.0);
+        let (mut h_scale, mut h_cross, mut h_bias) = (1e-9, 0.0, 1e-9);
+        for &(x, correct) in points {
+            let p = 1.0 / (1.0 + (-(scale * x + bias)).exp());
+            let weight = p * (1.0 - p);
+            g_scale += (p - target(correct)) * x;
+            g_bias += p - target(correct);
+            h_scale += weight * x * x;
+            h_cross += weight * x;
+            h_bias += weight;
+        }
+
+        let (d_scale, d_bias) = match method {
+            CalibrationMethod::Temperature => (g_scale / h_scale, 0.0),
+            CalibrationMethod::Platt => {
+                let det = h_scale * h_bias - h_cross * h_cross;
+                if det.abs() < 1e-12 {
+                    break;
+                }
+                (
+                    (h_bias * g_scale - h_cross * g_bias) / det,
+                    (h_scale * g_bias - h_cross * g_scale) / det,
+                )
+            }
+        };
+
+        let mut step = 1.0;
+        while step > 1e-6 && loss(scale - step * d_scale, bias - step * d_bias) > current {
+            step /= 2.0;
+        }
+        scale -= step * d_scale;
+        bias -= step * d_bias;
+        current = loss(scale, bias);
+        if !scale.is_finite() || !bias.is_finite() {
+            return LabelCalibration::default();
+        }
+        if step * (d_scale.abs() + d_bias.abs()) < 1e-7 {
+            break;
+        }
+    }
+
+    LabelCalibration {
+        scale: (scale as f32).max(MIN_SCALE),
+        bias: bias as f32,
+    }
+}
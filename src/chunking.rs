@@ -0,0 +1,207 @@
+use crate::processor::SentenceSplitter;
+use std::ops::Range;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct ChunkingConfig {
+    // Total subword budget of one encoder pass, prompt and special tokens included.
+    pub max_len: usize,
+    // Number of words shared by two consecutive windows, capped at half of
+    // the earlier window. Entities crossing a window edge are found whole when
+    // it exceeds the model's `max_width`.
+    pub overlap: usize,
+    // Number of windows sent to the session in one run.
+    pub batch_size: usize,
+    // When set, windows are packed with whole sentences instead of a fixed
+    // number of words, and every entity reports the sentence it belongs to.
+    pub sentence_splitter: Option<Arc<dyn SentenceSplitter>>,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        ChunkingConfig {
+            max_len: 512,
+            overlap: 32,
+            batch_size: 8,
+            sentence_splitter: None,
+        }
+    }
+}
+
+// A slice of one input text that is sent to the model on its own.
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub text_id: usize,
+    pub words: Range<usize>,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    // Whether the window boundary falls inside running text (as opposed to a
+    // text or sentence boundary), in which case spans touching it may be cut.
+    pub cut_start: bool,
+    pub cut_end: bool,
+    // Byte ranges of the previous and next windows, set on cut edges.
+    pub previous: Option<(usize, usize)>,
+    pub next: Option<(usize, usize)>,
+}
+
+impl Window {
+    // Whether a span found in this window, with byte offsets into the text, is
+    // kept. A span touching a cut edge may be truncated, so it is left to the
+    // neighbouring window when that one sees it away from both of its edges.
+    pub fn keeps(&self, start: usize, end: usize) -> bool {
+        let sees_inside =
+            |(byte_start, byte_end): (usize, usize)| byte_start < start && end < byte_end;
+        let left_to_next =
+            self.cut_end && end == self.byte_end && self.next.is_some_and(sees_inside);
+        let left_to_previous =
+            self.cut_start && start == self.byte_start && self.previous.is_some_and(sees_inside);
+        !(left_to_next || left_to_previous)
+    }
+}
+
+// Splits a sequence of words into overlapping word ranges so that the sum of
+// `token_counts` inside every range stays within `budget`. A single word larger
+// than the budget still gets a window of its own. Windows share at most half
+// of their words with the next one, so each moves forward by at least half.
+pub fn plan_windows(token_counts: &[usize], budget: usize, overlap: usize) -> Vec<Range<usize>> {
+    let mut windows = Vec::new();
+    let num_words = token_counts.len();
+    let mut start = 0;
+
+    while start < num_words {
+        let mut end = start;
+        let mut used = 0;
+        while end < num_words && (end == start || used + token_counts[end] <= budget) {
+            used += token_counts[end];
+            end += 1;
+        }
+        windows.push(start..end);
+
+        if end == num_words {
+            break;
+        }
+        start = end - overlap.min((end - start) / 2);
+    }
+
+    windows
+}
+
+// Packs whole sentences, given as word ranges, into windows that fit the
+// budget. A sentence that does not fit on its own is split with
+// `plan_windows`, and only those windows are marked as cut.
+pub fn plan_sentence_windows(
+    sentences: &[Range<usize>],
+    token_counts: &[usize],
+    budget: usize,
+    overlap: usize,
+) -> Vec<(Range<usize>, bool, bool)> {
+    let mut windows = Vec::new();
+    let mut current: Option<Range<usize>> = None;
+    let mut used = 0;
+
+    for sentence in sentences.iter().filter(|sentence| !sentence.is_empty()) {
+        let length: usize = token_counts[sentence.clone()].iter().sum();
+
+        if length > budget {
+            windows.extend(current.take().map(|words| (words, false, false)));
+            used = 0;
+            let parts = plan_windows(&token_counts[sentence.clone()], budget, overlap);
+            let num_parts = parts.len();
+            windows.extend(parts.into_iter().enumerate().map(|(i, part)| {
+                (
+                    sentence.start + part.start..sentence.start + part.end,
+                    i > 0,
+                    i + 1 < num_parts,
+                )
+            }));
+            continue;
+        }
+
+        match current.as_mut() {
+            Some(words) if used + length <= budget => {
+                words.end = sentence.end;
+                used += length;
+            }
+            _ => {
+                windows.extend(current.take().map(|words| (words, false, false)));
+                current = Some(sentence.clone());
+                used = length;
+            }
+        }
+    }
+    windows.extend(current.map(|words| (words, false, false)));
+
+    windows
+}
+
+// Maps sentence byte ranges to the ranges of words starting inside them.
+pub fn sentence_word_ranges(
+    sentences: &[(usize, usize)],
+    words_start_idx: &[usize],
+) -> Vec<Range<usize>> {
+    sentences
+        .iter()
+        .map(|&(start, end)| {
+            let first = words_start_idx.partition_point(|&word| word < start);
+            let last = words_start_idx.partition_point(|&word| word < end);
+            first..last
+        })
+        .collect()
+}
+
+// Builds the windows of one text from its word offsets. When `sentences` is
+// given, windows follow sentence boundaries.
+#[allow(clippy::too_many_arguments)]
+pub fn build_windows(
+    text_id: usize,
+    words_start_idx: &[usize],
+    words_end_idx: &[usize],
+    token_counts: &[usize],
+    sentences: Option<&[(usize, usize)]>,
+    budget: usize,
+    overlap: usize,
+) -> Vec<Window> {
+    let ranges = match sentences {
+        Some(sentences) => plan_sentence_windows(
+            &sentence_word_ranges(sentences, words_start_idx),
+            token_counts,
+            budget,
+            overlap,
+        ),
+        None => {
+            let ranges = plan_windows(token_counts, budget, overlap);
+            let num_windows = ranges.len();
+            ranges
+                .into_iter()
+                .enumerate()
+                .map(|(i, words)| (words, i > 0, i + 1 < num_windows))
+                .collect()
+        }
+    };
+
+    let bytes: Vec<(usize, usize)> = ranges
+        .iter()
+        .map(|(words, _, _)| (words_start_idx[words.start], words_end_idx[words.end - 1]))
+        .collect();
+    ranges
+        .into_iter()
+        .enumerate()
+        .map(|(i, (words, cut_start, cut_end))| Window {
+            text_id,
+            byte_start: bytes[i].0,
+            byte_end: bytes[i].1,
+            words,
+            cut_start,
+            cut_end,
+            previous: cut_start.then(|| bytes[i - 1]),
+            next: cut_end.then(|| bytes[i + 1]),
+        })
+        .collect()
+}
+
+// Index of the sentence containing byte offset `position`.
+pub fn sentence_index(sentences: &[(usize, usize)], position: usize) -> Option<usize> {
+    sentences
+        .partition_point(|&(start, _)| start <= position)
+        .checked_sub(1)
+}
@@ -0,0 +1,59 @@
+use crate::decoder::SpanMode;
+use crate::error::{GlinerError, Result};
+use serde::{Deserialize, Deserializer};
+use std::path::Path;
+
+// Settings a GLiNER checkpoint was trained with, as found in the
+// `gliner_config.json` of Hugging Face model directories. Fields missing from
+// the file keep their defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ModelConfig {
+    // Maximum number of words in a candidate span.
+    pub max_width: usize,
+    // GLiNER truncates texts to this many words. Here it is only read as the
+    // subword budget of one encoder pass for label grouping: lists whose
+    // prompt takes more than half of it are split into several passes. Texts
+    // are never truncated; longer ones go through `Gliner::inference_long`.
+    pub max_len: usize,
+    pub ent_token: String,
+    pub sep_token: String,
+    // Span or token-level decoding. Detected from the model when unset.
+    #[serde(deserialize_with = "deserialize_span_mode")]
+    pub span_mode: Option<SpanMode>,
+    // "whitespace" (default), "cjk" for character level Chinese and Japanese,
+    // or "regex" to split on `words_splitter_pattern`.
+    pub words_splitter_type: String,
+    pub words_splitter_pattern: Option<String>,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        ModelConfig {
+            max_width: 12,
+            max_len: 512,
+            ent_token: "<<ENT>>".to_string(),
+            sep_token: "<<SEP>>".to_string(),
+            span_mode: None,
+            words_splitter_type: "whitespace".to_string(),
+            words_splitter_pattern: None,
+        }
+    }
+}
+
+fn deserialize_span_mode<'de, D>(deserializer: D) -> std::result::Result<Option<SpanMode>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let span_mode: Option<String> = Option::deserialize(deserializer)?;
+    Ok(span_mode.map(|span_mode| SpanMode::from_config(&span_mode)))
+}
+
+impl ModelConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content =
+            std::fs::read_to_string(path).map_err(|e| GlinerError::file("reading", path, e))?;
+        serde_json::from_str(&content).map_err(|e| GlinerError::file("parsing", path, e))
+    }
+}
@@ -1,30 +1,218 @@
+use crate::calibration::LabelCalibration;
 use crate::types::RawInferenceResult;
+use ort::Session;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::Range;
 
 // Helper functions
-fn is_nested(idx1: &[usize], idx2: &[usize]) -> bool {
-    (idx1[0] <= idx2[0] && idx1[1] >= idx2[1]) || (idx2[0] <= idx1[0] && idx2[1] >= idx1[1])
+fn is_nested(idx1: (usize, usize), idx2: (usize, usize)) -> bool {
+    (idx1.0 <= idx2.0 && idx1.1 >= idx2.1) || (idx2.0 <= idx1.0 && idx2.1 >= idx1.1)
 }
 
-fn has_overlapping(idx1: &[usize], idx2: &[usize], multi_label: bool) -> bool {
-    if idx1[0..2] == idx2[0..2] {
+fn has_overlapping(idx1: (usize, usize), idx2: (usize, usize), multi_label: bool) -> bool {
+    if idx1 == idx2 {
         return !multi_label;
     }
-    !(idx1[0] > idx2[1] || idx2[0] > idx1[1])
+    !(idx1.0 > idx2.1 || idx2.0 > idx1.1)
 }
 
-fn has_overlapping_nested(idx1: &[usize], idx2: &[usize], multi_label: bool) -> bool {
-    if idx1[0..2] == idx2[0..2] {
+fn has_overlapping_nested(idx1: (usize, usize), idx2: (usize, usize), multi_label: bool) -> bool {
+    if idx1 == idx2 {
         return !multi_label;
     }
-    !(idx1[0] > idx2[1] || idx2[0] > idx1[1] || is_nested(idx1, idx2))
+    !(idx1.0 > idx2.1 || idx2.0 > idx1.1 || is_nested(idx1, idx2))
 }
 
 fn sigmoid(x: f32) -> f32 {
     1.0 / (1.0 + (-x).exp())
 }
 
+// Inverse of `sigmoid`, used to compare logits against probability thresholds
+// without computing the sigmoid of rejected cells.
+fn logit(p: f32) -> f32 {
+    if p <= 0.0 {
+        f32::NEG_INFINITY
+    } else if p >= 1.0 {
+        f32::INFINITY
+    } else {
+        (p / (1.0 - p)).ln()
+    }
+}
+
+// Raw logit each class needs to pass its calibrated threshold, along with its
+// calibration.
+fn class_thresholds(
+    thresholds: &[f32],
+    calibration: &[LabelCalibration],
+) -> Vec<(f32, LabelCalibration)> {
+    thresholds
+        .iter()
+        .enumerate()
+        .map(|(entity, &threshold)| {
+            let calibration = calibration.get(entity).copied().unwrap_or_default();
+            (calibration.raw_logit(logit(threshold)), calibration)
+        })
+        .collect()
+}
+
+// A span scoring over the threshold of its class, with byte offsets into the
+// text it was found in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanCandidate {
+    pub start: usize,
+    pub end: usize,
+    pub class_id: usize,
+    pub score: f32,
+    // Scores of every class for the same offsets, only filled when label scores
+    // are requested.
+    pub label_scores: Vec<f32>,
+}
+
+impl SpanCandidate {
+    pub fn new(start: usize, end: usize, class_id: usize, score: f32) -> Self {
+        SpanCandidate {
+            start,
+            end,
+            class_id,
+            score,
+            label_scores: Vec::new(),
+        }
+    }
+
+    pub fn offsets(&self) -> (usize, usize) {
+        (self.start, self.end)
+    }
+}
+
+// Offsets of kept spans sorted by start. A span can only overlap kept spans
+// starting at most `max_len` bytes before it, so overlap checks only look at
+// that range instead of every kept span.
+#[derive(Default)]
+struct SpanIndex {
+    offsets: Vec<(usize, usize)>,
+    max_len: usize,
+}
+
+impl SpanIndex {
+    fn insert(&mut self, span: (usize, usize)) {
+        let position = self.offsets.partition_point(|&kept| kept < span);
+        self.offsets.insert(position, span);
+        self.max_len = self.max_len.max(span.1 - span.0);
+    }
+
+    fn any_conflict(
+        &self,
+        span: (usize, usize),
+        conflicts: impl Fn((usize, usize), (usize, usize)) -> bool,
+    ) -> bool {
+        let lowest_start = span.0.saturating_sub(self.max_len);
+        let from = self
+            .offsets
+            .partition_point(|&(start, _)| start < lowest_start);
+        let to = self.offsets.partition_point(|&(start, _)| start <= span.1);
+        self.offsets[from..to.max(from)]
+            .iter()
+            .any(|&kept| conflicts(span, kept))
+    }
+}
+
+// How overlapping candidate spans are resolved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    // Keeps spans in decreasing score order unless they overlap a kept span.
+    #[default]
+    Greedy,
+    // Keeps the set of spans with the highest total score.
+    MaxTotalScore,
+    // Keeps the set of spans with the highest total log-odds `ln(p / (1 - p))`.
+    // Spans scoring under 0.5 lower the total and are never kept.
+    MaxLogOdds,
+}
+
+impl SelectionStrategy {
+    fn weight(&self, score: f32) -> f32 {
+        match self {
+            SelectionStrategy::MaxLogOdds => {
+                let score = score.clamp(1e-6, 1.0 - 1e-6);
+                (score / (1.0 - score)).ln()
+            }
+            _ => score,
+        }
+    }
+}
+
+// Weighted interval scheduling over `[start, end, value]` intervals, where two
+// intervals conflict when they share a position (ends are inclusive, as in
+// `has_overlapping`). Returns the indices of the best non-conflicting set.
+fn schedule(intervals: &[(usize, usize, f32)]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..intervals.len()).collect();
+    order.sort_by_key(|&id| intervals[id].1);
+
+    // best[j]: best total using the first j intervals of `order`.
+    let mut best = vec![0.0f32; order.len() + 1];
+    let mut previous = vec![0; order.len()];
+    for (j, &id) in order.iter().enumerate() {
+        let (start, _, value) = intervals[id];
+        previous[j] = order[..j].partition_point(|&other| intervals[other].1 < start);
+        best[j + 1] = best[j].max(value + best[previous[j]]);
+    }
+
+    let mut selected = Vec::new();
+    let mut j = order.len();
+    while j > 0 {
+        let id = order[j - 1];
+        if intervals[id].2 + best[previous[j - 1]] > best[j - 1] {
+            selected.push(id);
+            j = previous[j - 1];
+        } else {
+            j -= 1;
+        }
+    }
+    selected
+}
+
+// Max-pools candidates of prompts standing for the same label, e.g. a
+// description and its aliases. Class ids are mapped through `canonical`, and
+// only the best of the candidates left with the same offsets and label is
+// kept. Label scores, when filled, are pooled into `num_labels` scores.
+pub fn pool_labels(spans: &mut Vec<SpanCandidate>, canonical: &[usize], num_labels: usize) {
+    for span in spans.iter_mut() {
+        span.class_id = canonical[span.class_id];
+        if !span.label_scores.is_empty() {
+            let mut pooled = vec![0.0f32; num_labels];
+            for (&label, &score) in canonical.iter().zip(&span.label_scores) {
+                pooled[label] = pooled[label].max(score);
+            }
+            span.label_scores = pooled;
+        }
+    }
+    spans.sort_by(|a, b| {
+        (a.start, a.end, a.class_id)
+            .cmp(&(b.start, b.end, b.class_id))
+            .then(b.score.total_cmp(&a.score))
+    });
+    spans.dedup_by_key(|span| (span.start, span.end, span.class_id));
+}
+
+// Maps the class ids of spans decoded for the prompts `group` of a label list
+// to positions in the whole list.
+pub fn remap_group(
+    spans: impl IntoIterator<Item = SpanCandidate>,
+    group: &Range<usize>,
+) -> Vec<SpanCandidate> {
+    spans
+        .into_iter()
+        .map(|mut span| {
+            span.class_id += group.start;
+            span
+        })
+        .collect()
+}
+
 pub trait Decoder {
+    #[allow(clippy::too_many_arguments)]
     fn decode(
         &self,
         batch_size: usize,
@@ -41,13 +229,66 @@ pub trait Decoder {
         threshold: f32,
         multi_label: bool,
     ) -> RawInferenceResult;
+
+    // Same as `decode`, but stops before overlap resolution and returns the
+    // candidates of every batch item. Scores of each class are calibrated with
+    // its entry of `calibration` before being compared against its entry of
+    // `thresholds`.
+    #[allow(clippy::too_many_arguments)]
+    fn decode_candidates(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+        batch_words_start_idx: &[Vec<usize>],
+        batch_words_end_idx: &[Vec<usize>],
+        model_output: &[f32],
+        thresholds: &[f32],
+        calibration: &[LabelCalibration],
+    ) -> Vec<Vec<SpanCandidate>>;
+
+    // Score of every class for the span covering words `start_word..=end_word`
+    // of batch item `batch`, computed the same way as candidate scores.
+    #[allow(clippy::too_many_arguments)]
+    fn span_scores(
+        &self,
+        batch: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+        start_word: usize,
+        end_word: usize,
+        model_output: &[f32],
+        calibration: &[LabelCalibration],
+    ) -> Vec<f32>;
+
+    // Number of values the model is expected to output for a batch.
+    fn output_size(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+    ) -> usize;
+
+    // Resolves overlapping spans with the same overlap rules `decode` applies
+    // to a single model output.
+    fn select_spans(
+        &self,
+        spans: &mut Vec<SpanCandidate>,
+        flat_ner: bool,
+        multi_label: bool,
+        strategy: SelectionStrategy,
+    ) -> Vec<SpanCandidate>;
 }
 
 // BaseDecoder trait
 pub trait BaseDecoder {
-    fn new(config: HashMap<String, String>) -> Self
+    fn new() -> Self
     where
         Self: Sized;
+    #[allow(clippy::too_many_arguments)]
     fn decode(
         &self,
         batch_size: usize,
@@ -67,110 +308,142 @@ pub trait BaseDecoder {
 
     fn greedy_search(
         &self,
-        spans: &mut Vec<Vec<f32>>,
+        spans: &mut Vec<SpanCandidate>,
         flat_ner: bool,
         multi_label: bool,
-    ) -> Vec<Vec<f32>> {
-        let has_ov = if flat_ner {
-            Box::new(move |idx1: &[usize], idx2: &[usize]| has_overlapping(idx1, idx2, multi_label))
-                as Box<dyn Fn(&[usize], &[usize]) -> bool>
-        } else {
-            Box::new(move |idx1: &[usize], idx2: &[usize]| {
+    ) -> Vec<SpanCandidate> {
+        let has_ov = |idx1: (usize, usize), idx2: (usize, usize)| {
+            if flat_ner {
+                has_overlapping(idx1, idx2, multi_label)
+            } else {
                 has_overlapping_nested(idx1, idx2, multi_label)
-            }) as Box<dyn Fn(&[usize], &[usize]) -> bool>
+            }
         };
 
-        let mut new_list: Vec<Vec<f32>> = Vec::new();
-        spans.sort_by(|a, b| b.last().unwrap().partial_cmp(a.last().unwrap()).unwrap());
+        let mut new_list: Vec<SpanCandidate> = Vec::new();
+        let mut kept = SpanIndex::default();
+        spans.sort_by(|a, b| b.score.total_cmp(&a.score));
 
         for b in spans.iter() {
-            let mut flag = false;
-            for new_span in &new_list {
-                if has_ov(
-                    &b[0..2].iter().map(|&x| x as usize).collect::<Vec<usize>>(),
-                    &new_span[0..2]
-                        .iter()
-                        .map(|&x| x as usize)
-                        .collect::<Vec<usize>>(),
-                ) {
-                    flag = true;
-                    break;
-                }
-            }
-            if !flag {
+            if !kept.any_conflict(b.offsets(), has_ov) {
+                kept.insert(b.offsets());
                 new_list.push(b.clone());
             }
         }
 
-        new_list.sort_by(|a, b| a[0].partial_cmp(&b[0]).unwrap());
+        new_list.sort_by_key(|span| span.start);
         new_list
     }
-}
-
-// SpanDecoder struct
-pub struct SpanDecoder {
-    config: HashMap<String, String>,
-}
-
-impl BaseDecoder for SpanDecoder {
-    fn new(config: HashMap<String, String>) -> Self {
-        SpanDecoder { config }
-    }
 
-    fn decode(
+    // Same overlap rules as `greedy_search`, but keeps the set of spans that
+    // maximizes the total weight given by `strategy` instead of committing to
+    // the best span first. Spans sharing the same offsets are weighed together:
+    // all of them with `multi_label`, otherwise only the best one. With nested
+    // entities, a span is worth its own weight plus the best set of spans it
+    // contains.
+    fn optimal_search(
         &self,
-        batch_size: usize,
-        input_length: usize,
-        max_width: usize,
-        num_entities: usize,
-        texts: &[Vec<String>],
-        batch_ids: &[usize],
-        batch_words_start_idx: &[Vec<usize>],
-        batch_words_end_idx: &[Vec<usize>],
-        id_to_class: &HashMap<usize, String>,
-        model_output: &[f32],
+        spans: &[SpanCandidate],
         flat_ner: bool,
-        threshold: f32,
         multi_label: bool,
-    ) -> RawInferenceResult {
-        let mut spans: Vec<Vec<Vec<f32>>> = vec![vec![]; batch_size];
-
-        let batch_padding = input_length * max_width * num_entities;
-        // println!("batch_padding {:?}", batch_padding);
-        let start_token_padding = max_width * num_entities;
-        let end_token_padding = num_entities;
-
-        for (id, &value) in model_output.iter().enumerate() {
-            let batch = id / batch_padding;
-            let start_token = (id / start_token_padding) % input_length;
-            let end_token = start_token + ((id / end_token_padding) % max_width);
-
-            let entity = id % num_entities;
+        strategy: SelectionStrategy,
+    ) -> Vec<SpanCandidate> {
+        // Offsets of each group of spans and the spans weighed in it.
+        let mut offsets: Vec<(usize, usize)> = Vec::new();
+        let mut members: Vec<Vec<&SpanCandidate>> = Vec::new();
+        let mut group_ids: HashMap<(usize, usize), usize> = HashMap::new();
+        for span in spans
+            .iter()
+            .filter(|span| strategy.weight(span.score) > 0.0)
+        {
+            let key = span.offsets();
+            let id = *group_ids.entry(key).or_insert_with(|| {
+                offsets.push(key);
+                members.push(Vec::new());
+                offsets.len() - 1
+            });
+            members[id].push(span);
+        }
+        for group in members.iter_mut() {
+            group.sort_by(|a, b| b.score.total_cmp(&a.score));
+            if !multi_label {
+                group.truncate(1);
+            }
+        }
 
-            let prob = sigmoid(value);
+        let weights: Vec<f32> = members
+            .iter()
+            .map(|group| group.iter().map(|span| strategy.weight(span.score)).sum())
+            .collect();
 
-            if prob >= threshold
-                && start_token < batch_words_start_idx[batch].len()
-                && end_token < batch_words_end_idx[batch].len()
-            {
-                let global_batch = batch_ids[batch];
-                let start_idx = batch_words_start_idx[batch][start_token];
-                let end_idx = batch_words_end_idx[batch][end_token];
+        // Value of each group, and the groups directly inside it that are kept
+        // along with it.
+        let mut values = weights.clone();
+        let mut children: Vec<Vec<usize>> = vec![vec![]; offsets.len()];
+        if !flat_ner {
+            let mut by_length: Vec<usize> = (0..offsets.len()).collect();
+            by_length.sort_by_key(|&id| offsets[id].1 - offsets[id].0);
+            for &id in &by_length {
+                let (start, end) = offsets[id];
+                let inside: Vec<usize> = (0..offsets.len())
+                    .filter(|&other| {
+                        other != id && start <= offsets[other].0 && offsets[other].1 <= end
+                    })
+                    .collect();
+                let intervals: Vec<(usize, usize, f32)> = inside
+                    .iter()
+                    .map(|&other| (offsets[other].0, offsets[other].1, values[other]))
+                    .collect();
+                children[id] = schedule(&intervals)
+                    .into_iter()
+                    .map(|index| inside[index])
+                    .collect();
+                values[id] =
+                    weights[id] + children[id].iter().map(|&child| values[child]).sum::<f32>();
+            }
+        }
 
-                // println!("entity: {}", id_to_class.get(&entity).unwrap());
-                // let span_text = texts[global_batch][0][start_idx..end_idx].to_string();
+        let intervals: Vec<(usize, usize, f32)> = offsets
+            .iter()
+            .zip(&values)
+            .map(|(&(start, end), &value)| (start, end, value))
+            .collect();
+        let mut pending = schedule(&intervals);
+        let mut new_list: Vec<SpanCandidate> = Vec::new();
+        while let Some(id) = pending.pop() {
+            new_list.extend(members[id].iter().map(|&span| span.clone()));
+            pending.extend(&children[id]);
+        }
 
-                // println!("span_text: {}", span_text);
+        new_list.sort_by_key(|span| span.start);
+        new_list
+    }
 
-                spans[batch].push(vec![
-                    start_idx as f32,
-                    end_idx as f32,
-                    entity as f32, // Changed from (entity + 1) to entity
-                    prob,
-                ]);
-            }
+    // Resolves overlaps among `spans` with the given strategy.
+    fn select_with(
+        &self,
+        spans: &mut Vec<SpanCandidate>,
+        flat_ner: bool,
+        multi_label: bool,
+        strategy: SelectionStrategy,
+    ) -> Vec<SpanCandidate> {
+        match strategy {
+            SelectionStrategy::Greedy => self.greedy_search(spans, flat_ner, multi_label),
+            _ => self.optimal_search(spans, flat_ner, multi_label, strategy),
         }
+    }
 
+    // Runs `greedy_search` on the candidates of every batch item and maps the
+    // kept spans to their text and label.
+    fn format_spans(
+        &self,
+        spans: &mut [Vec<SpanCandidate>],
+        texts: &[Vec<String>],
+        batch_ids: &[usize],
+        id_to_class: &HashMap<usize, String>,
+        flat_ner: bool,
+        multi_label: bool,
+    ) -> RawInferenceResult {
         let mut all_selected_spans: RawInferenceResult = Vec::new();
 
         for (id, res_i) in spans.iter_mut().enumerate() {
@@ -178,10 +451,10 @@ impl BaseDecoder for SpanDecoder {
             let batch_spans: Vec<(String, usize, usize, String, f32)> = selected_spans
                 .into_iter()
                 .map(|span| {
-                    let start_idx = span[0] as usize;
-                    let end_idx = span[1] as usize;
-                    let entity_id = span[2] as usize;
-                    let prob = span[3];
+                    let start_idx = span.start;
+                    let end_idx = span.end;
+                    let entity_id = span.class_id;
+                    let prob = span.score;
                     let global_batch = batch_ids[id];
 
                     // Ensure indices are within bounds
@@ -209,6 +482,111 @@ impl BaseDecoder for SpanDecoder {
     }
 }
 
+// SpanDecoder struct
+pub struct SpanDecoder;
+
+impl SpanDecoder {
+    // Collects every span whose calibrated score passes the threshold of its
+    // class, with byte offsets taken from the word boundaries of each batch
+    // item. Only cells of spans that fit in the words of their batch item are
+    // read.
+    #[allow(clippy::too_many_arguments)]
+    pub fn collect_spans(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+        batch_words_start_idx: &[Vec<usize>],
+        batch_words_end_idx: &[Vec<usize>],
+        model_output: &[f32],
+        thresholds: &[f32],
+        calibration: &[LabelCalibration],
+    ) -> Vec<Vec<SpanCandidate>> {
+        let mut spans: Vec<Vec<SpanCandidate>> = vec![vec![]; batch_size];
+        let classes = class_thresholds(thresholds, calibration);
+
+        for (batch, batch_spans) in spans.iter_mut().enumerate() {
+            let words_start_idx = &batch_words_start_idx[batch];
+            let words_end_idx = &batch_words_end_idx[batch];
+            let num_words = words_start_idx
+                .len()
+                .min(words_end_idx.len())
+                .min(input_length);
+
+            for (start_token, &start_idx) in words_start_idx[..num_words].iter().enumerate() {
+                for width in 0..max_width.min(num_words - start_token) {
+                    let offset =
+                        ((batch * input_length + start_token) * max_width + width) * num_entities;
+                    let Some(cells) = model_output.get(offset..offset + num_entities) else {
+                        continue;
+                    };
+                    let end_idx = words_end_idx[start_token + width];
+
+                    for (entity, (&value, &(threshold, calibration))) in
+                        cells.iter().zip(&classes).enumerate()
+                    {
+                        if value >= threshold {
+                            batch_spans.push(SpanCandidate::new(
+                                start_idx,
+                                end_idx,
+                                entity,
+                                sigmoid(calibration.logit(value)),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        spans
+    }
+}
+
+impl BaseDecoder for SpanDecoder {
+    fn new() -> Self {
+        SpanDecoder
+    }
+
+    fn decode(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+        texts: &[Vec<String>],
+        batch_ids: &[usize],
+        batch_words_start_idx: &[Vec<usize>],
+        batch_words_end_idx: &[Vec<usize>],
+        id_to_class: &HashMap<usize, String>,
+        model_output: &[f32],
+        flat_ner: bool,
+        threshold: f32,
+        multi_label: bool,
+    ) -> RawInferenceResult {
+        let mut spans = self.collect_spans(
+            batch_size,
+            input_length,
+            max_width,
+            num_entities,
+            batch_words_start_idx,
+            batch_words_end_idx,
+            model_output,
+            &vec![threshold; num_entities],
+            &vec![LabelCalibration::default(); num_entities],
+        );
+
+        self.format_spans(
+            &mut spans,
+            texts,
+            batch_ids,
+            id_to_class,
+            flat_ner,
+            multi_label,
+        )
+    }
+}
+
 impl Decoder for SpanDecoder {
     fn decode(
         &self,
@@ -247,4 +625,338 @@ impl Decoder for SpanDecoder {
             multi_label,
         )
     }
+
+    fn decode_candidates(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+        batch_words_start_idx: &[Vec<usize>],
+        batch_words_end_idx: &[Vec<usize>],
+        model_output: &[f32],
+        thresholds: &[f32],
+        calibration: &[LabelCalibration],
+    ) -> Vec<Vec<SpanCandidate>> {
+        self.collect_spans(
+            batch_size,
+            input_length,
+            max_width,
+            num_entities,
+            batch_words_start_idx,
+            batch_words_end_idx,
+            model_output,
+            thresholds,
+            calibration,
+        )
+    }
+
+    fn span_scores(
+        &self,
+        batch: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+        start_word: usize,
+        end_word: usize,
+        model_output: &[f32],
+        calibration: &[LabelCalibration],
+    ) -> Vec<f32> {
+        let width = end_word.saturating_sub(start_word);
+        if width >= max_width {
+            return vec![0.0; num_entities];
+        }
+        let offset = ((batch * input_length + start_word) * max_width + width) * num_entities;
+        (0..num_entities)
+            .map(|entity| {
+                let calibration = calibration.get(entity).copied().unwrap_or_default();
+                model_output
+                    .get(offset + entity)
+                    .map_or(0.0, |&value| sigmoid(calibration.logit(value)))
+            })
+            .collect()
+    }
+
+    fn output_size(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+    ) -> usize {
+        batch_size * input_length * max_width * num_entities
+    }
+
+    fn select_spans(
+        &self,
+        spans: &mut Vec<SpanCandidate>,
+        flat_ner: bool,
+        multi_label: bool,
+        strategy: SelectionStrategy,
+    ) -> Vec<SpanCandidate> {
+        self.select_with(spans, flat_ner, multi_label, strategy)
+    }
+}
+
+// How a model scores entities, which decides both the session inputs and the
+// layout of its output. Mirrors the `span_mode` field of GLiNER configs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanMode {
+    // Logits shaped `[batch, words, max_width, classes]`, one per candidate span.
+    Span,
+    // Logits shaped `[batch, words, classes, 3]` with start, end and inside scores.
+    TokenLevel,
+}
+
+impl SpanMode {
+    pub fn from_config(span_mode: &str) -> Self {
+        match span_mode {
+            "token_level" | "token-level" => SpanMode::TokenLevel,
+            _ => SpanMode::Span,
+        }
+    }
+
+    // Span models take `span_idx`/`span_mask` inputs; token-level exports do not
+    // and end their output with a dimension of size 3.
+    pub fn detect(session: &Session) -> Self {
+        if session.inputs.iter().any(|input| input.name == "span_idx") {
+            return SpanMode::Span;
+        }
+        let token_shaped = session
+            .outputs
+            .first()
+            .and_then(|output| output.output_type.tensor_dimensions())
+            .is_some_and(|dims| dims.len() == 4 && dims[3] == 3);
+        if token_shaped {
+            SpanMode::TokenLevel
+        } else {
+            SpanMode::Span
+        }
+    }
+}
+
+// TokenDecoder struct
+pub struct TokenDecoder;
+
+impl TokenDecoder {
+    // Builds spans from words whose start and end scores pass the threshold of
+    // a class, with every word in between scoring over it as inside. As in
+    // GLiNER, the span score is the mean of the calibrated inside
+    // probabilities of its words. Thresholds are compared as logits.
+    #[allow(clippy::too_many_arguments)]
+    pub fn collect_spans(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        num_entities: usize,
+        batch_words_start_idx: &[Vec<usize>],
+        batch_words_end_idx: &[Vec<usize>],
+        model_output: &[f32],
+        thresholds: &[f32],
+        calibration: &[LabelCalibration],
+    ) -> Vec<Vec<SpanCandidate>> {
+        let mut spans: Vec<Vec<SpanCandidate>> = vec![vec![]; batch_size];
+        let classes = class_thresholds(thresholds, calibration);
+        let value = |batch: usize, word: usize, entity: usize, kind: usize| {
+            let id = ((batch * input_length + word) * num_entities + entity) * 3 + kind;
+            model_output.get(id).copied().unwrap_or(f32::NEG_INFINITY)
+        };
+
+        for (batch, batch_spans) in spans.iter_mut().enumerate() {
+            let words_start_idx = &batch_words_start_idx[batch];
+            let words_end_idx = &batch_words_end_idx[batch];
+            let num_words = words_start_idx
+                .len()
+                .min(words_end_idx.len())
+                .min(input_length);
+
+            for (entity, &(threshold, calibration)) in classes.iter().enumerate().take(num_entities)
+            {
+                for (start_token, &start_idx) in words_start_idx[..num_words].iter().enumerate() {
+                    let start_value = value(batch, start_token, entity, 0);
+                    if start_value < threshold {
+                        continue;
+                    }
+
+                    let mut inside_sum = 0.0;
+                    for (end_token, &end_idx) in words_end_idx[..num_words]
+                        .iter()
+                        .enumerate()
+                        .skip(start_token)
+                    {
+                        let inside = value(batch, end_token, entity, 2);
+                        if inside < threshold {
+                            break;
+                        }
+                        inside_sum += sigmoid(calibration.logit(inside));
+
+                        let end_value = value(batch, end_token, entity, 1);
+                        if end_value >= threshold {
+                            batch_spans.push(SpanCandidate::new(
+                                start_idx,
+                                end_idx,
+                                entity,
+                                inside_sum / (end_token - start_token + 1) as f32,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        spans
+    }
+}
+
+impl BaseDecoder for TokenDecoder {
+    fn new() -> Self {
+        TokenDecoder
+    }
+
+    fn decode(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        _max_width: usize,
+        num_entities: usize,
+        texts: &[Vec<String>],
+        batch_ids: &[usize],
+        batch_words_start_idx: &[Vec<usize>],
+        batch_words_end_idx: &[Vec<usize>],
+        id_to_class: &HashMap<usize, String>,
+        model_output: &[f32],
+        flat_ner: bool,
+        threshold: f32,
+        multi_label: bool,
+    ) -> RawInferenceResult {
+        let mut spans = self.collect_spans(
+            batch_size,
+            input_length,
+            num_entities,
+            batch_words_start_idx,
+            batch_words_end_idx,
+            model_output,
+            &vec![threshold; num_entities],
+            &vec![LabelCalibration::default(); num_entities],
+        );
+
+        self.format_spans(
+            &mut spans,
+            texts,
+            batch_ids,
+            id_to_class,
+            flat_ner,
+            multi_label,
+        )
+    }
+}
+
+impl Decoder for TokenDecoder {
+    fn decode(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        max_width: usize,
+        num_entities: usize,
+        texts: &[String],
+        batch_ids: &[usize],
+        batch_words_start_idx: &[Vec<usize>],
+        batch_words_end_idx: &[Vec<usize>],
+        id_to_class: &HashMap<usize, String>,
+        model_output: &[f32],
+        flat_ner: bool,
+        threshold: f32,
+        multi_label: bool,
+    ) -> RawInferenceResult {
+        let texts_vec: Vec<Vec<String>> = texts.iter().map(|s| vec![s.clone()]).collect();
+
+        BaseDecoder::decode(
+            self,
+            batch_size,
+            input_length,
+            max_width,
+            num_entities,
+            &texts_vec,
+            batch_ids,
+            batch_words_start_idx,
+            batch_words_end_idx,
+            id_to_class,
+            model_output,
+            flat_ner,
+            threshold,
+            multi_label,
+        )
+    }
+
+    fn decode_candidates(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        _max_width: usize,
+        num_entities: usize,
+        batch_words_start_idx: &[Vec<usize>],
+        batch_words_end_idx: &[Vec<usize>],
+        model_output: &[f32],
+        thresholds: &[f32],
+        calibration: &[LabelCalibration],
+    ) -> Vec<Vec<SpanCandidate>> {
+        self.collect_spans(
+            batch_size,
+            input_length,
+            num_entities,
+            batch_words_start_idx,
+            batch_words_end_idx,
+            model_output,
+            thresholds,
+            calibration,
+        )
+    }
+
+    fn span_scores(
+        &self,
+        batch: usize,
+        input_length: usize,
+        _max_width: usize,
+        num_entities: usize,
+        start_word: usize,
+        end_word: usize,
+        model_output: &[f32],
+        calibration: &[LabelCalibration],
+    ) -> Vec<f32> {
+        let value = |word: usize, entity: usize, kind: usize| {
+            let id = ((batch * input_length + word) * num_entities + entity) * 3 + kind;
+            model_output.get(id).copied().unwrap_or(f32::NEG_INFINITY)
+        };
+
+        (0..num_entities)
+            .map(|entity| {
+                let calibration = calibration.get(entity).copied().unwrap_or_default();
+                let lowest = (start_word..=end_word)
+                    .map(|word| value(word, entity, 2))
+                    .fold(value(start_word, entity, 0), f32::min)
+                    .min(value(end_word, entity, 1));
+                sigmoid(calibration.logit(lowest))
+            })
+            .collect()
+    }
+
+    fn output_size(
+        &self,
+        batch_size: usize,
+        input_length: usize,
+        _max_width: usize,
+        num_entities: usize,
+    ) -> usize {
+        batch_size * input_length * num_entities * 3
+    }
+
+    fn select_spans(
+        &self,
+        spans: &mut Vec<SpanCandidate>,
+        flat_ner: bool,
+        multi_label: bool,
+        strategy: SelectionStrategy,
+    ) -> Vec<SpanCandidate> {
+        self.select_with(spans, flat_ner, multi_label, strategy)
+    }
 }
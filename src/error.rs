@@ -0,0 +1,50 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GlinerError {
+    // The model, tokenizer or configuration is unusable, e.g. a tokenizer
+    // without the GLiNER marker tokens or an invalid pattern.
+    #[error("Initialization error: {0}")]
+    InitializationError(String),
+    // The request cannot be run, e.g. an uninitialized model or a label prompt
+    // leaving no room for text.
+    #[error("Inference error: {0}")]
+    InferenceError(String),
+    #[error("No entity labels were given")]
+    NoLabels,
+    #[error("Error {action} {path}: {message}")]
+    FileError {
+        action: &'static str,
+        path: PathBuf,
+        message: String,
+    },
+    #[error("Tokenizer error: {0}")]
+    TokenizerError(String),
+    #[error("Invalid input shape: {0}")]
+    InputShapeError(String),
+    #[error("Session run failed: {0}")]
+    SessionError(#[from] ort::Error),
+    // The model output cannot be read or does not match the configuration.
+    #[error("Decoding error: {0}")]
+    DecodingError(String),
+}
+
+pub type Result<T> = std::result::Result<T, GlinerError>;
+
+impl GlinerError {
+    pub(crate) fn file(action: &'static str, path: &Path, error: impl Display) -> Self {
+        GlinerError::FileError {
+            action,
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<ndarray::ShapeError> for GlinerError {
+    fn from(error: ndarray::ShapeError) -> Self {
+        GlinerError::InputShapeError(error.to_string())
+    }
+}
@@ -1,15 +1,24 @@
-use crate::decoder::BaseDecoder;
-use crate::decoder::SpanDecoder;
+use crate::batching::BatchingConfig;
+use crate::calibration::Calibration;
+use crate::chunking::{sentence_index, ChunkingConfig};
+use crate::config::ModelConfig;
+use crate::decoder::{BaseDecoder, Decoder, SpanDecoder, SpanMode, TokenDecoder};
+use crate::error::{GlinerError, Result};
 use crate::model::Model;
+use crate::offsets::{set_offsets, set_word_offsets};
 use crate::onnxwrapper::ONNXWrapper;
-use crate::processor::SpanProcessor;
-use crate::processor::{Processor, WhitespaceTokenSplitter};
+use crate::options::{InferenceItem, InferenceOptions};
+use crate::processor::{join_words, BatchProcessor, SpanProcessor, TokenProcessor, Words};
+use crate::recognizers::RecognizerRegistry;
 use crate::types::InferenceResultSingle;
-use crate::types::{EntityResult, InferenceResultMultiple, RawInferenceResult};
-use anyhow::Result;
-use std::collections::HashMap;
+use crate::types::{
+    EntityResult, EntityTree, InferenceResultMultiple, LabelScore, RawInferenceResult,
+    RawLabelScores,
+};
+use std::path::Path;
+use std::sync::Arc;
 use tokenizers::Tokenizer;
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct InitConfig {
     pub tokenizer_path: String,
     pub model_path: String,
@@ -17,53 +26,154 @@ pub struct InitConfig {
 
     // pub onnx_settings: ONNXSettings,
     pub max_width: Option<usize>,
+    // Subword budget of one encoder pass, only used to group labels: lists
+    // whose prompt takes more than half of it are split into several passes.
+    // Texts are not truncated to it.
+    pub max_len: Option<usize>,
+    // Whether the model is span based or token level. Detected from the model
+    // inputs and output shape when not set.
+    pub span_mode: Option<SpanMode>,
+    // How texts are split into words: "whitespace" (default), "cjk" or "regex"
+    // together with `words_splitter_pattern`.
+    pub words_splitter_type: Option<String>,
+    pub words_splitter_pattern: Option<String>,
 }
 
 pub struct Gliner {
     config: InitConfig,
+    model_config: ModelConfig,
+    calibration: Calibration,
+    recognizers: Arc<RecognizerRegistry>,
     model: Option<Model>,
 }
 
 impl Gliner {
     pub fn new(config: InitConfig) -> Self {
+        let defaults = ModelConfig::default();
+        let model_config = ModelConfig {
+            max_width: config.max_width.unwrap_or(defaults.max_width),
+            max_len: config.max_len.unwrap_or(defaults.max_len),
+            span_mode: config.span_mode,
+            words_splitter_type: config
+                .words_splitter_type
+                .clone()
+                .unwrap_or(defaults.words_splitter_type.clone()),
+            words_splitter_pattern: config.words_splitter_pattern.clone(),
+            ..defaults
+        };
         Gliner {
             config,
+            model_config,
+            calibration: Calibration::default(),
+            recognizers: Arc::new(RecognizerRegistry::default()),
             model: None,
         }
     }
 
+    // Loads a model directory in the Hugging Face GLiNER layout:
+    // `gliner_config.json`, `tokenizer.json` and the ONNX export under `onnx/`
+    // (`model.onnx`, or `model_quantized.onnx` when that is the only one). A
+    // `calibration.json` in the directory is applied to scores. `num_threads`
+    // is the number of intra-op threads of the session, as in `InitConfig`.
+    pub async fn from_dir<P: AsRef<Path>>(path: P, num_threads: Option<usize>) -> Result<Self> {
+        let dir = path.as_ref();
+        let model_config = ModelConfig::from_file(dir.join("gliner_config.json"))?;
+
+        let tokenizer_path = dir.join("tokenizer.json");
+        if !tokenizer_path.is_file() {
+            return Err(GlinerError::file(
+                "loading",
+                &tokenizer_path,
+                "No tokenizer.json found",
+            ));
+        }
+        let calibration_path = dir.join("calibration.json");
+        let calibration = if calibration_path.is_file() {
+            Calibration::from_file(calibration_path)?
+        } else {
+            Calibration::default()
+        };
+        let model_path = [
+            "onnx/model.onnx",
+            "onnx/model_quantized.onnx",
+            "model.onnx",
+            "model_quantized.onnx",
+        ]
+        .iter()
+        .map(|candidate| dir.join(candidate))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| GlinerError::file("loading", dir, "No ONNX model found"))?;
+
+        let mut gliner = Gliner {
+            config: InitConfig {
+                tokenizer_path: tokenizer_path.to_string_lossy().into_owned(),
+                model_path: model_path.to_string_lossy().into_owned(),
+                num_threads,
+                max_width: Some(model_config.max_width),
+                max_len: Some(model_config.max_len),
+                span_mode: model_config.span_mode,
+                words_splitter_type: Some(model_config.words_splitter_type.clone()),
+                words_splitter_pattern: model_config.words_splitter_pattern.clone(),
+            },
+            model_config,
+            calibration,
+            recognizers: Arc::new(RecognizerRegistry::default()),
+            model: None,
+        };
+        gliner.initialize().await?;
+        Ok(gliner)
+    }
+
     pub async fn initialize(&mut self) -> Result<()> {
         let tokenizer = Tokenizer::from_file(self.config.tokenizer_path.clone())
-            .map_err(|e| anyhow::anyhow!("Error initializing tokenizer: {}", e))?;
+            .map_err(|e| GlinerError::file("loading", self.config.tokenizer_path.as_ref(), e))?;
         let onnx_wrapper =
-            ONNXWrapper::new(self.config.model_path.clone(), self.config.num_threads);
-
-        let processor = SpanProcessor::new(
-            HashMap::from([(
-                "max_width".to_string(),
-                self.config.max_width.unwrap_or(12).to_string(),
-            )]),
-            tokenizer,
-        );
-        let decoder = SpanDecoder::new(HashMap::from([(
-            "max_width".to_string(),
-            self.config.max_width.unwrap_or(12).to_string(),
-        )]));
-
-        let model = Model::new(
-            HashMap::from([(
-                "max_width".to_string(),
-                self.config.max_width.unwrap_or(12).to_string(),
-            )]),
-            processor,
-            Box::new(decoder),
-            onnx_wrapper.session,
-        );
+            ONNXWrapper::new(self.config.model_path.clone(), self.config.num_threads)?;
+
+        let mut config = self.model_config.clone();
+        let span_mode = *config
+            .span_mode
+            .get_or_insert_with(|| SpanMode::detect(&onnx_wrapper.session));
+        let (processor, decoder): (Box<dyn BatchProcessor>, Box<dyn Decoder>) = match span_mode {
+            SpanMode::Span => (
+                Box::new(SpanProcessor::new(config.clone(), tokenizer)?),
+                Box::new(SpanDecoder::new()),
+            ),
+            SpanMode::TokenLevel => (
+                Box::new(TokenProcessor::new(config.clone(), tokenizer)?),
+                Box::new(TokenDecoder::new()),
+            ),
+        };
+
+        let mut model = Model::new(config, processor, decoder, onnx_wrapper.session);
+        model.set_calibration(self.calibration.clone())?;
+        model.set_recognizers(self.recognizers.clone());
 
         self.model = Some(model);
         self.model.as_mut().unwrap().initialize().await
     }
 
+    // Per-label calibration applied to scores before thresholds, replacing the
+    // one loaded by `from_dir`. `Calibration::default()` restores raw scores.
+    // Scales below the minimum are rejected as for loaded calibrations.
+    pub fn set_calibration(&mut self, calibration: Calibration) -> Result<()> {
+        calibration.validate()?;
+        if let Some(model) = self.model.as_mut() {
+            model.set_calibration(calibration.clone())?;
+        }
+        self.calibration = calibration;
+        Ok(())
+    }
+
+    // Regex and gazetteer recognizers run on every text. Their matches are
+    // returned with the model entities, overlaps being resolved among both.
+    pub fn set_recognizers(&mut self, recognizers: RecognizerRegistry) {
+        self.recognizers = Arc::new(recognizers);
+        if let Some(model) = self.model.as_mut() {
+            model.set_recognizers(self.recognizers.clone());
+        }
+    }
+
     pub async fn inference(
         &self,
         texts: &[String],
@@ -71,72 +181,251 @@ impl Gliner {
         flat_ner: bool,
         threshold: f32,
     ) -> Result<InferenceResultMultiple> {
-        let model = self
-            .model
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Model is not initialized. Call initialize() first."))?;
-        let result: RawInferenceResult = model
-            .inference(texts, entities, flat_ner, threshold)
+        let model = self.model.as_ref().ok_or_else(|| {
+            GlinerError::InferenceError(
+                "Model is not initialized. Call initialize() first.".to_string(),
+            )
+        })?;
+        let options = InferenceOptions::new(flat_ner, threshold);
+        let (result, label_scores, words) = model.inference(texts, entities, &options).await?;
+        Ok(self.finish(texts, &words, result, label_scores, &options))
+    }
+
+    // Same as `inference`, with thresholds, overlap and multi-label handling
+    // taken from `options`.
+    pub async fn inference_with_options(
+        &self,
+        texts: &[String],
+        entities: &[&str],
+        options: &InferenceOptions,
+    ) -> Result<InferenceResultMultiple> {
+        let model = self.model.as_ref().ok_or_else(|| {
+            GlinerError::InferenceError(
+                "Model is not initialized. Call initialize() first.".to_string(),
+            )
+        })?;
+        let (result, label_scores, words) = model.inference(texts, entities, options).await?;
+        Ok(self.finish(texts, &words, result, label_scores, options))
+    }
+
+    // Same as `inference`, but a span may be returned once per label scoring
+    // over `threshold` instead of only with its best label.
+    pub async fn inference_multi_label(
+        &self,
+        texts: &[String],
+        entities: &[&str],
+        flat_ner: bool,
+        threshold: f32,
+    ) -> Result<InferenceResultMultiple> {
+        let model = self.model.as_ref().ok_or_else(|| {
+            GlinerError::InferenceError(
+                "Model is not initialized. Call initialize() first.".to_string(),
+            )
+        })?;
+        let options = InferenceOptions {
+            multi_label: true,
+            ..InferenceOptions::new(flat_ner, threshold)
+        };
+        let (result, label_scores, words) = model.inference(texts, entities, &options).await?;
+        Ok(self.finish(texts, &words, result, label_scores, &options))
+    }
+
+    // Same as `inference_with_options` with nested entities kept regardless of
+    // `options.flat_ner`, returned as one tree per text.
+    pub async fn inference_tree(
+        &self,
+        texts: &[String],
+        entities: &[&str],
+        options: &InferenceOptions,
+    ) -> Result<Vec<EntityTree>> {
+        let options = InferenceOptions {
+            flat_ner: false,
+            ..options.clone()
+        };
+        let results = self
+            .inference_with_options(texts, entities, &options)
             .await?;
-        Ok(self.map_raw_result_to_response(result))
+        Ok(results.iter().map(InferenceResultSingle::to_tree).collect())
     }
 
-    // pub async fn inference_with_chunking(
-    //     &self,
-    //     texts: &[String],
-    //     entities: &[String],
-    //     flat_ner: bool,
-    //     threshold: f32,
-    // ) -> Result<InferenceResultMultiple> {
-    //     let model = self
-    //         .model
-    //         .as_ref()
-    //         .ok_or_else(|| anyhow::anyhow!("Model is not initialized. Call initialize() first."))?;
-    //     let result = model
-    //         .inference_with_chunking(texts, entities, flat_ner, threshold)
-    //         .await?;
-    //     Ok(self.map_raw_result_to_response(result))
-    // }
-
-    // fn map_raw_result_to_response(
-    //     &self,
-    //     raw_result: RawInferenceResult,
-    // ) -> InferenceResultMultiple {
-    //     raw_result
-    //         .into_iter()
-    //         .map(|individual_result| {
-    //             individual_result
-    //                 .into_iter()
-    //                 .map(|(span_text, start, end, label, score)| EntityResult {
-    //                     span_text,
-    //                     start,
-    //                     end,
-    //                     label,
-    //                     score,
-    //                 })
-    //                 .collect()
-    //         })
-    //         .collect()
-    // }
+    // Runs inference on texts that each come with their own labels and
+    // options, e.g. documents of different schemas, in a single batch.
+    // Results are returned in the order of `items`.
+    pub async fn inference_items(
+        &self,
+        items: &[InferenceItem],
+    ) -> Result<InferenceResultMultiple> {
+        let model = self.model.as_ref().ok_or_else(|| {
+            GlinerError::InferenceError(
+                "Model is not initialized. Call initialize() first.".to_string(),
+            )
+        })?;
+        let (result, label_scores, words) = model.inference_items(items).await?;
+        Ok(items
+            .iter()
+            .zip(words.chunks(1))
+            .zip(result.into_iter().zip(label_scores))
+            .flat_map(|((item, text_words), (text_result, text_scores))| {
+                self.finish(
+                    std::slice::from_ref(&item.text),
+                    text_words,
+                    vec![text_result],
+                    vec![text_scores],
+                    &item.options,
+                )
+            })
+            .collect())
+    }
+
+    // Runs inference on any number of texts, grouped into micro-batches of
+    // similar length. Results are returned in the order of `texts`.
+    pub async fn inference_batched(
+        &self,
+        texts: &[String],
+        entities: &[&str],
+        options: &InferenceOptions,
+        batching: &BatchingConfig,
+    ) -> Result<InferenceResultMultiple> {
+        let model = self.model.as_ref().ok_or_else(|| {
+            GlinerError::InferenceError(
+                "Model is not initialized. Call initialize() first.".to_string(),
+            )
+        })?;
+        let (result, label_scores, words) = model
+            .inference_batched(texts, entities, options, batching)
+            .await?;
+        Ok(self.finish(texts, &words, result, label_scores, options))
+    }
+
+    // Runs inference on texts longer than the encoder context by splitting each
+    // of them into overlapping windows and merging the entities found in each.
+    pub async fn inference_long(
+        &self,
+        texts: &[String],
+        entities: &[&str],
+        options: &InferenceOptions,
+        chunking: &ChunkingConfig,
+    ) -> Result<InferenceResultMultiple> {
+        let model = self.model.as_ref().ok_or_else(|| {
+            GlinerError::InferenceError(
+                "Model is not initialized. Call initialize() first.".to_string(),
+            )
+        })?;
+        let (result, label_scores, words, sentences) = model
+            .inference_long(texts, entities, options, chunking)
+            .await?;
+        let mut response = self.finish(texts, &words, result, label_scores, options);
+        for (text_result, text_sentences) in response.iter_mut().zip(&sentences) {
+            for entity in text_result.entities.iter_mut() {
+                entity.sentence_index = sentence_index(text_sentences, entity.start);
+            }
+        }
+        Ok(response)
+    }
+
+    // Runs inference on texts already split into words by an external
+    // tokenizer, e.g. to align entities with its tokens. The words of a text
+    // are passed to the model as they are and joined with single spaces for
+    // `span_text`. Entities get the indices of the words they cover, and
+    // `start` and `end` are taken from `offsets`, which holds the `(start,
+    // end)` of every word in its original text, in any unit. Without offsets,
+    // they are byte offsets into the joined words.
+    pub async fn inference_pretokenized(
+        &self,
+        words: &[Vec<String>],
+        offsets: Option<&[Vec<(usize, usize)>]>,
+        entities: &[&str],
+        options: &InferenceOptions,
+    ) -> Result<InferenceResultMultiple> {
+        let model = self.model.as_ref().ok_or_else(|| {
+            GlinerError::InferenceError(
+                "Model is not initialized. Call initialize() first.".to_string(),
+            )
+        })?;
+        if let Some(offsets) = offsets {
+            if offsets.len() != words.len()
+                || offsets.iter().zip(words).any(|(o, w)| o.len() != w.len())
+            {
+                return Err(GlinerError::InputShapeError(
+                    "Offsets must be given for every word of every text".to_string(),
+                ));
+            }
+        }
+        let (texts, text_words): (Vec<String>, Vec<Words>) =
+            words.iter().map(|words| join_words(words)).unzip();
+        let (result, label_scores) = model
+            .inference_words(&texts, &text_words, entities, options)
+            .await?;
+        let mut response = self.finish(&texts, &text_words, result, label_scores, options);
+        if let Some(offsets) = offsets {
+            for (text_result, text_offsets) in response.iter_mut().zip(offsets) {
+                set_word_offsets(&mut text_result.entities, text_offsets);
+            }
+        }
+        Ok(response)
+    }
+
+    // Turns raw results into entities, applies post-processing and sets the
+    // word indices and extra offsets of every entity from the `words` the
+    // texts were split into for the model pass.
+    fn finish(
+        &self,
+        texts: &[String],
+        words: &[Words],
+        raw_result: RawInferenceResult,
+        label_scores: RawLabelScores,
+        options: &InferenceOptions,
+    ) -> InferenceResultMultiple {
+        let mut response = self.map_raw_result_to_response(raw_result, label_scores);
+        options.post_processing.apply_all(texts, &mut response);
+        for ((result, text), (_, words_start_idx, words_end_idx)) in
+            response.iter_mut().zip(texts).zip(words)
+        {
+            if result.entities.is_empty() {
+                continue;
+            }
+            set_offsets(
+                text,
+                words_start_idx,
+                words_end_idx,
+                &mut result.entities,
+                options.offset_unit,
+            );
+        }
+        response
+    }
 
     fn map_raw_result_to_response(
         &self,
         raw_result: RawInferenceResult,
+        label_scores: RawLabelScores,
     ) -> Vec<InferenceResultSingle> {
         raw_result
             .into_iter()
-            .map(|individual_result| InferenceResultSingle {
-                entities: individual_result
-                    .into_iter()
-                    .map(|(span_text, start, end, label, score)| EntityResult {
-                        span_text,
-                        start,
-                        end,
-                        label,
-                        score,
-                    })
-                    .collect(),
-            })
+            .zip(label_scores)
+            .map(
+                |(individual_result, individual_scores)| InferenceResultSingle {
+                    entities: individual_result
+                        .into_iter()
+                        .zip(individual_scores)
+                        .map(
+                            |((span_text, start, end, label, score), label_scores)| EntityResult {
+                                span_text,
+                                start,
+                                end,
+                                label,
+                                score,
+                                sentence_index: None,
+                                label_scores: label_scores
+                                    .into_iter()
+                                    .map(|(label, score)| LabelScore { label, score })
+                                    .collect(),
+                                ..Default::default()
+                            },
+                        )
+                        .collect(),
+                },
+            )
             .collect()
     }
 }
@@ -0,0 +1,91 @@
+use crate::error::{GlinerError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// How a label is put to the model. The label keeps its name in results, while
+// the model is prompted with `description` (the name when unset) and every
+// alias, the best of their scores being the score of the label.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LabelDefinition {
+    pub description: Option<String>,
+    pub aliases: Vec<String>,
+}
+
+impl LabelDefinition {
+    pub fn new(description: &str) -> Self {
+        LabelDefinition {
+            description: Some(description.to_string()),
+            aliases: Vec::new(),
+        }
+    }
+
+    pub fn with_alias(mut self, alias: &str) -> Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+}
+
+// Labels of one inference call along with the prompts they expand to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelSet {
+    names: Vec<String>,
+    prompts: Vec<String>,
+    // Position in `names` of the label each prompt stands for.
+    canonical: Vec<usize>,
+}
+
+impl LabelSet {
+    // Expands `entities` with their entry in `definitions`. Labels without one
+    // are prompted with their name. At least one label is needed.
+    pub fn new(entities: &[&str], definitions: &HashMap<String, LabelDefinition>) -> Result<Self> {
+        if entities.is_empty() {
+            return Err(GlinerError::NoLabels);
+        }
+        let mut prompts = Vec::with_capacity(entities.len());
+        let mut canonical = Vec::with_capacity(entities.len());
+        for (id, &entity) in entities.iter().enumerate() {
+            let definition = definitions.get(entity);
+            let description = definition
+                .and_then(|definition| definition.description.as_deref())
+                .unwrap_or(entity);
+            let aliases = definition.map(|definition| definition.aliases.as_slice());
+            for prompt in std::iter::once(description)
+                .chain(aliases.unwrap_or_default().iter().map(String::as_str))
+            {
+                prompts.push(prompt.to_string());
+                canonical.push(id);
+            }
+        }
+        Ok(LabelSet {
+            names: entities.iter().map(|entity| entity.to_string()).collect(),
+            prompts,
+            canonical,
+        })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.names.iter().map(String::as_str).collect()
+    }
+
+    pub fn prompts(&self) -> Vec<&str> {
+        self.prompts.iter().map(String::as_str).collect()
+    }
+
+    pub fn canonical(&self) -> &[usize] {
+        &self.canonical
+    }
+
+    // Whether every label is prompted with its name alone.
+    pub fn is_plain(&self) -> bool {
+        self.prompts == self.names
+    }
+
+    // Name of the label each prompt stands for, indexed by prompt.
+    pub fn prompt_names(&self) -> Vec<&str> {
+        self.canonical
+            .iter()
+            .map(|&id| self.names[id].as_str())
+            .collect()
+    }
+}
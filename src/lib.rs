@@ -1,12 +1,28 @@
 // glinerrust/src/lib.rs
 
+pub mod batching;
+pub mod calibration;
+pub mod chunking;
+pub mod config;
 pub mod decoder;
+pub mod error;
 pub mod gliner;
+pub mod labels;
 pub mod model;
+pub mod offsets;
 pub mod onnxwrapper;
+pub mod options;
+pub mod postprocess;
 pub mod processor;
+pub mod pseudonymize;
+pub mod recognizers;
+pub mod redact;
 pub mod types;
 
+pub use error::GlinerError;
 pub use gliner::Gliner;
+pub use options::{InferenceItem, InferenceOptions};
 pub use types::InferenceResultSingle;
-pub use types::{EntityResult, InferenceResultMultiple, RawInferenceResult};
+pub use types::{
+    EntityNode, EntityResult, EntityTree, InferenceResultMultiple, LabelScore, RawInferenceResult,
+};
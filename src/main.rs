@@ -1,16 +1,5 @@
 use anyhow::Result;
-use gliner::InitConfig;
-
-mod decoder;
-mod gliner;
-mod model;
-mod onnxwrapper;
-mod processor;
-mod types;
-
-pub use gliner::Gliner;
-use types::InferenceResultSingle;
-pub use types::{EntityResult, InferenceResultMultiple, RawInferenceResult};
+use glinerrust::{gliner::InitConfig, types::InferenceResultSingle, Gliner};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -20,6 +9,7 @@ async fn main() -> Result<()> {
         max_width: Some(12),
         model_path: "./model_quantized.onnx".to_string(),
         num_threads: Some(4),
+        ..Default::default()
     });
     // let mut gliner = Gliner::new(InitConfig {
     //     tokenizer_path:
@@ -39,7 +29,7 @@ async fn main() -> Result<()> {
     //     num_threads: Some(4),
     // });
 
-    let _ = gliner.initialize().await?;
+    gliner.initialize().await?;
 
     let input_texts = vec![
 
@@ -1,23 +1,39 @@
-use crate::processor::SpanProcessor;
-use crate::types::RawInferenceResult;
-use crate::{decoder::Decoder, processor::Processor};
-use anyhow::{Ok, Result};
-use ndarray::Array;
-use ort::SessionInputValue;
-use ort::{Session, Value};
-use std::borrow::Cow;
-use std::collections::HashMap;
+use crate::batching::{plan_batches, BatchingConfig};
+use crate::calibration::{Calibration, LabelCalibration};
+use crate::chunking::{build_windows, ChunkingConfig, Window};
+use crate::config::ModelConfig;
+use crate::decoder::{pool_labels, remap_group, Decoder, SpanCandidate};
+use crate::error::{GlinerError, Result};
+use crate::labels::LabelSet;
+use crate::options::{InferenceItem, InferenceOptions};
+use crate::processor::{BatchProcessor, Words};
+use crate::recognizers::RecognizerRegistry;
+use crate::types::{RawInferenceResult, RawLabelScores};
+use ort::Session;
+use std::ops::Range;
+use std::sync::Arc;
+// Labels a text is prompted with, along with their groups and the options
+// the text is run with.
+#[derive(Clone, Copy)]
+struct RowLabels<'a> {
+    labels: &'a LabelSet,
+    groups: &'a [Range<usize>],
+    options: &'a InferenceOptions,
+}
+
 pub struct Model {
-    config: HashMap<String, String>,
-    processor: SpanProcessor,
+    config: ModelConfig,
+    processor: Box<dyn BatchProcessor>,
     decoder: Box<dyn Decoder>,
     session: Session,
+    calibration: Calibration,
+    recognizers: Arc<RecognizerRegistry>,
 }
 
 impl Model {
     pub fn new(
-        config: HashMap<String, String>,
-        processor: SpanProcessor,
+        config: ModelConfig,
+        processor: Box<dyn BatchProcessor>,
         decoder: Box<dyn Decoder>,
         session: Session,
     ) -> Self {
@@ -26,9 +42,29 @@ impl Model {
             processor,
             decoder,
             session,
+            calibration: Calibration::default(),
+            recognizers: Arc::new(RecognizerRegistry::default()),
         }
     }
 
+    // Calibration applied to the scores of every label before thresholding.
+    pub fn set_calibration(&mut self, calibration: Calibration) -> Result<()> {
+        calibration.validate()?;
+        self.calibration = calibration;
+        Ok(())
+    }
+
+    // Words of `text` along with their start and end byte offsets.
+    pub fn tokenize_text(&self, text: &str) -> Words {
+        self.processor.processor().tokenize_text(text)
+    }
+
+    // Recognizers whose matches compete with the model candidates in overlap
+    // resolution.
+    pub fn set_recognizers(&mut self, recognizers: Arc<RecognizerRegistry>) {
+        self.recognizers = recognizers;
+    }
+
     pub async fn initialize(&mut self) -> Result<()> {
         // If there's any initialization needed, do it here
         Ok(())
@@ -85,176 +121,497 @@ impl Model {
     //     Ok(session_input)
     // }
 
+    // Candidate spans of every text, given by its words, with class ids
+    // remapped to positions in the names of the labels of its row. Words
+    // without subwords are left out, and texts left without words have no
+    // candidates and are not passed to the model, which does not accept empty
+    // sequences.
+    async fn collect_candidates(
+        &self,
+        words: &[Words],
+        rows: &[RowLabels<'_>],
+    ) -> Result<Vec<Vec<SpanCandidate>>> {
+        let processor = self.processor.processor();
+        let words: Vec<Words> = words
+            .iter()
+            .map(|text_words| processor.drop_empty_words(text_words))
+            .collect();
+        let words = words.as_slice();
+        let non_empty: Vec<usize> = (0..words.len())
+            .filter(|&id| !words[id].0.is_empty())
+            .collect();
+        if non_empty.len() == words.len() {
+            return self.run_groups(words, rows).await;
+        }
+
+        let mut candidates: Vec<Vec<SpanCandidate>> = vec![vec![]; words.len()];
+        if !non_empty.is_empty() {
+            let non_empty_words: Vec<Words> =
+                non_empty.iter().map(|&id| words[id].clone()).collect();
+            let non_empty_rows: Vec<RowLabels> = non_empty.iter().map(|&id| rows[id]).collect();
+            let spans = self.run_groups(&non_empty_words, &non_empty_rows).await?;
+            for (&id, spans) in non_empty.iter().zip(spans) {
+                candidates[id] = spans;
+            }
+        }
+        Ok(candidates)
+    }
+
+    // Runs one session pass per group of prompts and returns the candidate
+    // spans of every text, which must all have words. Pass `n` prompts each
+    // text with its `n`-th group, texts with fewer groups sitting it out, and
+    // the class dimension of its output is padded to the largest group. When
+    // `top_k` is set, each candidate also gets the scores of every label for
+    // its offsets.
+    async fn run_groups(
+        &self,
+        words: &[Words],
+        rows: &[RowLabels<'_>],
+    ) -> Result<Vec<Vec<SpanCandidate>>> {
+        let max_width = self.config.max_width;
+        let prompts: Vec<Vec<&str>> = rows.iter().map(|row| row.labels.prompts()).collect();
+        let thresholds: Vec<Vec<f32>> = rows
+            .iter()
+            .map(|row| row.options.thresholds(&row.labels.prompt_names()))
+            .collect();
+        let calibration: Vec<Vec<LabelCalibration>> = rows
+            .iter()
+            .map(|row| self.calibration.for_entities(&row.labels.prompt_names()))
+            .collect();
+        let mut candidates: Vec<Vec<SpanCandidate>> = vec![vec![]; words.len()];
+        // Output rows of every pass with their group, input length and class
+        // count, kept to score spans found by other passes.
+        let mut row_outputs = vec![Vec::new(); words.len()];
+        let passes = rows.iter().map(|row| row.groups.len()).max().unwrap_or(0);
+
+        for pass in 0..passes {
+            let batch: Vec<usize> = (0..rows.len())
+                .filter(|&id| pass < rows[id].groups.len())
+                .collect();
+            let batch_words: Vec<Words> = batch.iter().map(|&id| words[id].clone()).collect();
+            let batch_entities: Vec<&[&str]> = batch
+                .iter()
+                .map(|&id| &prompts[id][rows[id].groups[pass].clone()])
+                .collect();
+            let num_classes = batch_entities
+                .iter()
+                .map(|entities| entities.len())
+                .max()
+                .unwrap_or(0);
+            let (session_input, _, batch_tokens, words_start_idx, words_end_idx, text_lengths) =
+                self.processor.prepare_rows(&batch_words, &batch_entities)?;
+
+            let outputs = self.session.run(session_input)?;
+            let logits = outputs[0]
+                .try_extract_tensor::<f32>()
+                .map_err(|e| GlinerError::DecodingError(e.to_string()))?;
+            let logits = logits.as_slice().ok_or_else(|| {
+                GlinerError::DecodingError("Model output is not contiguous".to_string())
+            })?;
+            let input_length = text_lengths.iter().max().copied().unwrap_or(0);
+            let expected =
+                self.decoder
+                    .output_size(batch_tokens.len(), input_length, max_width, num_classes);
+            if logits.len() != expected {
+                return Err(GlinerError::DecodingError(format!(
+                    "Model output has {} values instead of {}, which does not match the configured span mode and max_width",
+                    logits.len(),
+                    expected
+                )));
+            }
+
+            // Rows are decoded one by one, each with the thresholds of its own
+            // labels, so that padded classes are never read.
+            let row_size = self
+                .decoder
+                .output_size(1, input_length, max_width, num_classes);
+            for (row, (&id, row_logits)) in
+                batch.iter().zip(logits.chunks(row_size.max(1))).enumerate()
+            {
+                let group = rows[id].groups[pass].clone();
+                let spans = self.decoder.decode_candidates(
+                    1,
+                    input_length,
+                    max_width,
+                    num_classes,
+                    &words_start_idx[row..=row],
+                    &words_end_idx[row..=row],
+                    row_logits,
+                    &thresholds[id][group.clone()],
+                    &calibration[id][group.clone()],
+                );
+                candidates[id].extend(remap_group(spans.into_iter().flatten(), &group));
+
+                if rows[id].options.top_k.is_some() {
+                    row_outputs[id].push((group, input_length, num_classes, row_logits.to_vec()));
+                }
+            }
+        }
+
+        for (id, spans) in candidates.iter_mut().enumerate() {
+            if rows[id].options.top_k.is_some() {
+                let (_, words_start_idx, words_end_idx) = &words[id];
+                for span in spans.iter_mut() {
+                    let start_word = words_start_idx
+                        .iter()
+                        .position(|&start| start == span.start);
+                    let end_word = words_end_idx.iter().position(|&end| end == span.end);
+                    for (group, input_length, num_classes, logits) in &row_outputs[id] {
+                        match (start_word, end_word) {
+                            (Some(start_word), Some(end_word)) => {
+                                let mut scores = self.decoder.span_scores(
+                                    0,
+                                    *input_length,
+                                    max_width,
+                                    *num_classes,
+                                    start_word,
+                                    end_word,
+                                    logits,
+                                    &calibration[id][group.clone()],
+                                );
+                                scores.truncate(group.len());
+                                span.label_scores.extend(scores)
+                            }
+                            _ => span.label_scores.extend(vec![0.0; group.len()]),
+                        }
+                    }
+                }
+            }
+
+            let labels = rows[id].labels;
+            if !labels.is_plain() {
+                pool_labels(spans, labels.canonical(), labels.names().len());
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    // Adds the matches of the recognizers to the candidates of each text and
+    // returns the labels class ids refer to: `entities`, followed by recognizer
+    // labels missing from them.
+    fn add_recognized<'a>(
+        &'a self,
+        texts: &[String],
+        entities: &[&'a str],
+        candidates: &mut [Vec<SpanCandidate>],
+    ) -> Vec<&'a str> {
+        let mut labels: Vec<&str> = entities.to_vec();
+        for (text, spans) in texts.iter().zip(candidates.iter_mut()) {
+            for (start, end, label, score) in self.recognizers.recognize(text) {
+                let class_id = labels
+                    .iter()
+                    .position(|&known| known == label)
+                    .unwrap_or_else(|| {
+                        labels.push(label);
+                        labels.len() - 1
+                    });
+                spans.push(SpanCandidate::new(start, end, class_id, score));
+            }
+        }
+        labels
+    }
+
+    // Resolves overlaps among the candidates of each text and turns the kept
+    // spans into results, along with their ranked label scores.
+    fn select_spans(
+        &self,
+        texts: &[String],
+        entities: &[&str],
+        candidates: &mut [Vec<SpanCandidate>],
+        options: &InferenceOptions,
+    ) -> (RawInferenceResult, RawLabelScores) {
+        candidates
+            .iter_mut()
+            .zip(texts)
+            .map(|(spans, text)| {
+                self.decoder
+                    .select_spans(
+                        spans,
+                        options.flat_ner,
+                        options.multi_label,
+                        options.selection,
+                    )
+                    .into_iter()
+                    .map(|span| {
+                        let start = span.start;
+                        let end = span.end;
+                        let mut label_scores: Vec<(String, f32)> = entities
+                            .iter()
+                            .zip(&span.label_scores)
+                            .map(|(entity, &score)| (entity.to_string(), score))
+                            .collect();
+                        label_scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+                        label_scores.truncate(options.top_k.unwrap_or(0));
+                        (
+                            (
+                                text.get(start..end).unwrap_or_default().to_string(),
+                                start,
+                                end,
+                                entities[span.class_id].to_string(),
+                                span.score,
+                            ),
+                            label_scores,
+                        )
+                    })
+                    .unzip::<_, _, Vec<_>, Vec<_>>()
+            })
+            .unzip()
+    }
+
     pub async fn inference(
         &self,
         texts: &[String],
         entities: &[&str],
-        flat_ner: bool,
-        threshold: f32,
-    ) -> Result<RawInferenceResult> {
-        // ) -> Result<()> {
-        let (
-            session_input,
-            id_to_class,
-            batch_tokens,
-            batch_words_start_idx,
-            batch_words_end_idx,
-            text_lengths,
-        ) = self.processor.prepare_batch(texts, entities);
-        // let (session_input, _, _) = self.processor.prepare_inputs(&batch);
-
-        // println!("Session input {:#?}", self.session.inputs);
-        // Run the model
-        let outputs = self.session.run(session_input).unwrap();
-        // println!("Outputs {:?}", outputs.);
-        let logits = outputs[0].try_extract_tensor::<f32>()?;
-        // println!("Logits {:?}", logits);
-        // Ok(())
-        // Extract logits
-        // let logits = outputs[0].try_extract::<f32>()?;
-        // let logits_shape = logits.shape();
-        // println!("Logits shape {:?}", logits_shape);
-
-        let batch_size = batch_tokens.len();
-        // println!("Batch size {:?}", batch_size);
-        let input_length = *text_lengths.iter().max().unwrap();
-        // println!("Input length {:?}", input_length);
-        let max_width = self.config["max_width"].parse::<usize>().unwrap();
-        let num_entities = entities.len();
-        // println!("Num entities {:?}", num_entities);
-        let batch_ids: Vec<usize> = (0..batch_size).collect();
-
-        let decoded_spans: RawInferenceResult = self.decoder.decode(
-            batch_size,
-            input_length,
-            max_width,
-            num_entities,
-            &texts,
-            &batch_ids,
-            &batch_words_start_idx,
-            &batch_words_end_idx,
-            &id_to_class,
-            logits.as_slice().unwrap(),
-            flat_ner,
-            threshold,
-            false, // multi_label is not used in the TypeScript version
-        );
-
-        // Ok(())
-
-        Ok(decoded_spans)
+        options: &InferenceOptions,
+    ) -> Result<(RawInferenceResult, RawLabelScores, Vec<Words>)> {
+        let words: Vec<Words> = texts.iter().map(|text| self.tokenize_text(text)).collect();
+        let (result, label_scores) = self
+            .inference_words(texts, &words, entities, options)
+            .await?;
+        Ok((result, label_scores, words))
     }
 
-    // pub async fn inference_with_chunking(
-    //     &self,
-    //     texts: &[String],
-    //     entities: &[String],
-    //     flat_ner: bool,
-    //     threshold: f32,
-    // ) -> Result<RawInferenceResult> {
-    //     let (class_to_id, id_to_class) = self.processor.create_mappings(entities);
-
-    //     let mut batch_ids = Vec::new();
-    //     let mut batch_tokens = Vec::new();
-    //     let mut batch_words_start_idx = Vec::new();
-    //     let mut batch_words_end_idx = Vec::new();
-
-    //     for (id, text) in texts.iter().enumerate() {
-    //         let (tokens, words_start_idx, words_end_idx) = self.processor.tokenize_text(text);
-    //         let num_sub_batches = (tokens.len() as f32 / 512.0).ceil() as usize;
-
-    //         for i in 0..num_sub_batches {
-    //             let start = i * 512;
-    //             let end = (start + 512).min(tokens.len());
-
-    //             batch_ids.push(id);
-    //             batch_tokens.push(tokens[start..end].to_vec());
-    //             batch_words_start_idx.push(words_start_idx[start..end].to_vec());
-    //             batch_words_end_idx.push(words_end_idx[start..end].to_vec());
-    //         }
-    //     }
-
-    //     let num_batches = (batch_ids.len() as f32 / 16.0).ceil() as usize;
-
-    //     let mut final_decoded_spans: RawInferenceResult = vec![vec![]; texts.len()];
-
-    //     for batch_id in 0..num_batches {
-    //         let start = batch_id * 16;
-    //         let end = (start + 16).min(batch_ids.len());
-
-    //         let curr_batch_tokens = &batch_tokens[start..end];
-    //         let curr_batch_words_start_idx = &batch_words_start_idx[start..end];
-    //         let curr_batch_words_end_idx = &batch_words_end_idx[start..end];
-    //         let curr_batch_ids = &batch_ids[start..end];
-
-    //         let (input_tokens, text_lengths, prompt_lengths) = self
-    //             .processor
-    //             .prepare_text_inputs(curr_batch_tokens, entities);
-
-    //         let (mut inputs_ids, mut attention_masks, mut words_masks) = self
-    //             .processor
-    //             .encode_inputs(&input_tokens, Some(&prompt_lengths));
-
-    //         self.processor.pad_array(&mut inputs_ids, 2);
-    //         self.processor.pad_array(&mut attention_masks, 2);
-    //         self.processor.pad_array(&mut words_masks, 2);
-
-    //         let (mut span_idxs, mut span_masks) = self
-    //             .processor
-    //             .prepare_spans(curr_batch_tokens, self.config["max_width"].parse().unwrap());
-
-    //         self.processor.pad_array(&mut span_idxs, 3);
-    //         self.processor.pad_array(&mut span_masks, 2);
-
-    //         let mut batch = HashMap::new();
-    //         batch.insert("inputsIds".to_string(), inputs_ids);
-    //         batch.insert("attentionMasks".to_string(), attention_masks);
-    //         batch.insert("wordsMasks".to_string(), words_masks);
-    //         batch.insert("textLengths".to_string(), text_lengths);
-    //         batch.insert("spanIdxs".to_string(), span_idxs);
-    //         batch.insert("spanMasks".to_string(), span_masks);
-    //         batch.insert("idToClass".to_string(), id_to_class.clone());
-    //         batch.insert("batchTokens".to_string(), curr_batch_tokens.to_vec());
-    //         batch.insert(
-    //             "batchWordsStartIdx".to_string(),
-    //             curr_batch_words_start_idx.to_vec(),
-    //         );
-    //         batch.insert(
-    //             "batchWordsEndIdx".to_string(),
-    //             curr_batch_words_end_idx.to_vec(),
-    //         );
-
-    //         let feeds = self.prepare_inputs(&batch)?;
-    //         let outputs = self.session.run(feeds)?;
-    //         let logits = outputs[0].try_extract::<f32>()?;
-    //         let logits_shape = logits.shape();
-
-    //         let batch_size = batch["batchTokens"].len();
-    //         let input_length = *batch["textLengths"].iter().flatten().max().unwrap();
-    //         let max_width = self.config["max_width"].parse::<usize>().unwrap();
-    //         let num_entities = entities.len();
-
-    //         let decoded_spans = self.decoder.decode(
-    //             batch_size,
-    //             input_length,
-    //             max_width,
-    //             num_entities,
-    //             &batch["batchTokens"],
-    //             curr_batch_ids,
-    //             &batch["batchWordsStartIdx"],
-    //             &batch["batchWordsEndIdx"],
-    //             &batch["idToClass"],
-    //             logits.as_slice().unwrap(),
-    //             flat_ner,
-    //             threshold,
-    //             false, // multi_label is not used in the TypeScript version
-    //         );
-
-    //         for (i, &original_text_id) in curr_batch_ids.iter().enumerate() {
-    //             final_decoded_spans[original_text_id].extend_from_slice(&decoded_spans[i]);
-    //         }
-    //     }
-
-    //     Ok(final_decoded_spans)
-    // }
+    // Same as `inference` for texts already split into `words`, whose offsets
+    // are byte offsets into `texts`.
+    pub async fn inference_words(
+        &self,
+        texts: &[String],
+        words: &[Words],
+        entities: &[&str],
+        options: &InferenceOptions,
+    ) -> Result<(RawInferenceResult, RawLabelScores)> {
+        let labels = LabelSet::new(entities, &options.labels)?;
+        let groups = self
+            .processor
+            .processor()
+            .label_groups(&labels.prompts(), self.config.max_len);
+        let row = RowLabels {
+            labels: &labels,
+            groups: &groups,
+            options,
+        };
+        let mut candidates = self
+            .collect_candidates(words, &vec![row; words.len()])
+            .await?;
+
+        let names = self.add_recognized(texts, entities, &mut candidates);
+        Ok(self.select_spans(texts, &names, &mut candidates, options))
+    }
+
+    // Runs every item in one batch, each text being prompted with its own
+    // labels and decoded with its own options. Results are returned in the
+    // order of `items`, along with the words of every text.
+    pub async fn inference_items(
+        &self,
+        items: &[InferenceItem],
+    ) -> Result<(RawInferenceResult, RawLabelScores, Vec<Words>)> {
+        let labels: Vec<LabelSet> = items
+            .iter()
+            .map(|item| LabelSet::new(&item.labels(), &item.options.labels))
+            .collect::<Result<_>>()?;
+        let groups: Vec<Vec<Range<usize>>> = labels
+            .iter()
+            .map(|labels| {
+                self.processor
+                    .processor()
+                    .label_groups(&labels.prompts(), self.config.max_len)
+            })
+            .collect();
+        let rows: Vec<RowLabels> = items
+            .iter()
+            .zip(&labels)
+            .zip(&groups)
+            .map(|((item, labels), groups)| RowLabels {
+                labels,
+                groups,
+                options: &item.options,
+            })
+            .collect();
+        let texts: Vec<String> = items.iter().map(|item| item.text.clone()).collect();
+        let words: Vec<Words> = texts.iter().map(|text| self.tokenize_text(text)).collect();
+        let mut candidates = self.collect_candidates(&words, &rows).await?;
+
+        let mut result = Vec::with_capacity(items.len());
+        let mut label_scores = Vec::with_capacity(items.len());
+        for (id, item) in items.iter().enumerate() {
+            let entities = item.labels();
+            let names = self.add_recognized(&texts[id..=id], &entities, &mut candidates[id..=id]);
+            let (text_result, text_scores) = self.select_spans(
+                &texts[id..=id],
+                &names,
+                &mut candidates[id..=id],
+                &item.options,
+            );
+            result.extend(text_result);
+            label_scores.extend(text_scores);
+        }
+        Ok((result, label_scores, words))
+    }
+
+    // Same as `inference`, but runs the texts in micro-batches of similar
+    // length instead of padding all of them to the longest one.
+    pub async fn inference_batched(
+        &self,
+        texts: &[String],
+        entities: &[&str],
+        options: &InferenceOptions,
+        batching: &BatchingConfig,
+    ) -> Result<(RawInferenceResult, RawLabelScores, Vec<Words>)> {
+        let words: Vec<Words> = texts.iter().map(|text| self.tokenize_text(text)).collect();
+        let processor = self.processor.processor();
+        let token_counts: Vec<usize> = words
+            .iter()
+            .map(|(tokens, _, _)| {
+                tokens
+                    .iter()
+                    .map(|token| processor.count_subwords(token).max(1))
+                    .sum()
+            })
+            .collect();
+        let labels = LabelSet::new(entities, &options.labels)?;
+        let groups = processor.label_groups(&labels.prompts(), self.config.max_len);
+        let row = RowLabels {
+            labels: &labels,
+            groups: &groups,
+            options,
+        };
+        let mut candidates: Vec<Vec<SpanCandidate>> = vec![vec![]; texts.len()];
+
+        for batch in plan_batches(&token_counts, batching) {
+            let batch_words: Vec<Words> = batch.iter().map(|&id| words[id].clone()).collect();
+            let batch_candidates = self
+                .collect_candidates(&batch_words, &vec![row; batch_words.len()])
+                .await?;
+            for (&id, spans) in batch.iter().zip(batch_candidates) {
+                candidates[id] = spans;
+            }
+        }
+
+        let names = self.add_recognized(texts, entities, &mut candidates);
+        let (result, label_scores) = self.select_spans(texts, &names, &mut candidates, options);
+        Ok((result, label_scores, words))
+    }
+
+    // Same as `inference` for texts of any length, which are split into
+    // windows. The sentences of every text are returned when
+    // `chunking.sentence_splitter` is set.
+    pub async fn inference_long(
+        &self,
+        texts: &[String],
+        entities: &[&str],
+        options: &InferenceOptions,
+        chunking: &ChunkingConfig,
+    ) -> Result<(
+        RawInferenceResult,
+        RawLabelScores,
+        Vec<Words>,
+        Vec<Vec<(usize, usize)>>,
+    )> {
+        let processor = self.processor.processor();
+        let labels = LabelSet::new(entities, &options.labels)?;
+        let prompts = labels.prompts();
+        let groups = self
+            .processor
+            .processor()
+            .label_groups(&prompts, chunking.max_len);
+        let row = RowLabels {
+            labels: &labels,
+            groups: &groups,
+            options,
+        };
+        let prompt_length = groups
+            .iter()
+            .map(|group| processor.prompt_token_count(&prompts[group.clone()]))
+            .max()
+            .unwrap_or(0);
+        if prompt_length >= chunking.max_len {
+            return Err(GlinerError::InferenceError(format!(
+                "Entity prompt takes {} tokens, which leaves no room for text within max_len {}",
+                prompt_length, chunking.max_len
+            )));
+        }
+        let budget = chunking.max_len - prompt_length;
+        let mut windows: Vec<Window> = Vec::new();
+        let mut sentences: Vec<Vec<(usize, usize)>> = Vec::with_capacity(texts.len());
+        let words: Vec<Words> = texts.iter().map(|text| self.tokenize_text(text)).collect();
+        for (text_id, (text, (tokens, words_start_idx, words_end_idx))) in
+            texts.iter().zip(&words).enumerate()
+        {
+            let token_counts: Vec<usize> = tokens
+                .iter()
+                .map(|token| processor.count_subwords(token).max(1))
+                .collect();
+            let text_sentences = chunking
+                .sentence_splitter
+                .as_ref()
+                .map(|splitter| splitter.split(text))
+                .unwrap_or_default();
+            windows.extend(build_windows(
+                text_id,
+                words_start_idx,
+                words_end_idx,
+                &token_counts,
+                chunking
+                    .sentence_splitter
+                    .as_ref()
+                    .map(|_| text_sentences.as_slice()),
+                budget,
+                chunking.overlap,
+            ));
+            sentences.push(text_sentences);
+        }
+
+        let mut candidates: Vec<Vec<SpanCandidate>> = vec![vec![]; texts.len()];
+
+        for batch in windows.chunks(chunking.batch_size.max(1)) {
+            let window_words: Vec<Words> = batch
+                .iter()
+                .map(|window| {
+                    let (tokens, starts, ends) = &words[window.text_id];
+                    let range = window.words.clone();
+                    (
+                        tokens[range.clone()].to_vec(),
+                        starts[range.clone()]
+                            .iter()
+                            .map(|start| start - window.byte_start)
+                            .collect(),
+                        ends[range]
+                            .iter()
+                            .map(|end| end - window.byte_start)
+                            .collect(),
+                    )
+                })
+                .collect();
+            let window_candidates = self
+                .collect_candidates(&window_words, &vec![row; window_words.len()])
+                .await?;
+
+            for (window, spans) in batch.iter().zip(window_candidates) {
+                for mut span in spans {
+                    span.start += window.byte_start;
+                    span.end += window.byte_start;
+                    if window.keeps(span.start, span.end) {
+                        candidates[window.text_id].push(span);
+                    }
+                }
+            }
+        }
+
+        let names = self.add_recognized(texts, entities, &mut candidates);
+
+        // Windows overlap, so the same span may have been found more than once.
+        for spans in candidates.iter_mut() {
+            spans.sort_by(|a, b| {
+                (a.start, a.end, a.class_id)
+                    .cmp(&(b.start, b.end, b.class_id))
+                    .then(b.score.total_cmp(&a.score))
+            });
+            spans.dedup_by_key(|span| (span.start, span.end, span.class_id));
+        }
+
+        let (merged, label_scores) = self.select_spans(texts, &names, &mut candidates, options);
+
+        Ok((merged, label_scores, words, sentences))
+    }
 }
@@ -0,0 +1,99 @@
+use crate::types::EntityResult;
+use serde::{Deserialize, Serialize};
+
+// Unit of the extra offsets set on entities. Byte offsets are always set in
+// `start` and `end`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffsetUnit {
+    #[default]
+    Bytes,
+    // Unicode code points, as used by Python strings.
+    Chars,
+    // UTF-16 code units, as used by JavaScript and Java strings.
+    Utf16,
+}
+
+impl OffsetUnit {
+    // Converts byte positions of `text`, sorted in increasing order, in a
+    // single pass over it. A position inside a character counts that
+    // character.
+    pub fn convert_sorted(&self, text: &str, positions: &[usize]) -> Vec<usize> {
+        if *self == OffsetUnit::Bytes {
+            return positions.to_vec();
+        }
+        let mut converted = Vec::with_capacity(positions.len());
+        let mut chars = text.char_indices().peekable();
+        let mut count = 0;
+        for &position in positions {
+            while let Some(&(offset, c)) = chars.peek() {
+                if offset >= position {
+                    break;
+                }
+                count += match self {
+                    OffsetUnit::Utf16 => c.len_utf16(),
+                    _ => 1,
+                };
+                chars.next();
+            }
+            converted.push(count);
+        }
+        converted
+    }
+}
+
+// Sets the word indices of `entities` found in `text`, from the byte
+// boundaries of its words, along with their offsets in `unit` unless it is
+// bytes. Word indices cover every word the entity overlaps.
+pub fn set_offsets(
+    text: &str,
+    words_start_idx: &[usize],
+    words_end_idx: &[usize],
+    entities: &mut [EntityResult],
+    unit: OffsetUnit,
+) {
+    for entity in entities.iter_mut() {
+        entity.word_start = words_end_idx.partition_point(|&end| end <= entity.start);
+        entity.word_end = words_start_idx.partition_point(|&start| start < entity.end);
+    }
+    if unit == OffsetUnit::Bytes {
+        return;
+    }
+
+    let mut positions: Vec<(usize, usize)> = entities
+        .iter()
+        .enumerate()
+        .flat_map(|(id, entity)| [(entity.start, 2 * id), (entity.end, 2 * id + 1)])
+        .collect();
+    positions.sort_unstable();
+    let sorted: Vec<usize> = positions.iter().map(|&(position, _)| position).collect();
+    let converted = unit.convert_sorted(text, &sorted);
+
+    for (&(_, slot), offset) in positions.iter().zip(converted) {
+        let entity = &mut entities[slot / 2];
+        let target = match (unit, slot % 2) {
+            (OffsetUnit::Chars, 0) => &mut entity.char_start,
+            (OffsetUnit::Chars, _) => &mut entity.char_end,
+            (_, 0) => &mut entity.utf16_start,
+            _ => &mut entity.utf16_end,
+        };
+        *target = Some(offset);
+    }
+}
+
+// Replaces the offsets of `entities` with the ones of the words they cover,
+// `offsets` holding the `(start, end)` of every word in the caller's text and
+// unit. Extra offsets, which refer to the joined words, are cleared.
+pub fn set_word_offsets(entities: &mut [EntityResult], offsets: &[(usize, usize)]) {
+    for entity in entities.iter_mut() {
+        if entity.word_start >= entity.word_end || entity.word_end > offsets.len() {
+            continue;
+        }
+        entity.start = offsets[entity.word_start].0;
+        entity.end = offsets[entity.word_end - 1].1;
+        entity.char_start = None;
+        entity.char_end = None;
+        entity.utf16_start = None;
+        entity.utf16_end = None;
+    }
+}
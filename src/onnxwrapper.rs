@@ -1,12 +1,6 @@
-use anyhow::anyhow;
-use anyhow::{Context, Result};
-use ort::{
-    inputs, Environment, Error, GraphOptimizationLevel, Session, SessionBuilder, SessionInputValue,
-};
-use std::borrow::Cow;
-use std::{path::Path, thread::available_parallelism};
-use tokenizers::{PaddingParams, Tokenizer};
-use tracing::{error, info};
+use crate::error::{GlinerError, Result};
+use ort::{GraphOptimizationLevel, Session};
+use std::thread::available_parallelism;
 
 pub struct ONNXWrapper {
     pub session: Session,
@@ -14,22 +8,23 @@ pub struct ONNXWrapper {
 }
 
 impl ONNXWrapper {
-    pub fn new(model_path: String, num_threads: Option<usize>) -> Self {
+    pub fn new(model_path: String, num_threads: Option<usize>) -> Result<Self> {
         // let model_dir = "model_quantized.onnx";
-        let threads = num_threads.unwrap_or(available_parallelism().unwrap().get() as usize);
+        let threads = num_threads
+            .unwrap_or_else(|| available_parallelism().map_or(1, |threads| threads.get()));
         let session = Session::builder()
-            .unwrap()
-            .with_optimization_level(GraphOptimizationLevel::Level3)
-            .unwrap()
-            .with_intra_threads(threads)
-            .unwrap()
-            .commit_from_file(model_path)
-            .unwrap();
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|builder| builder.with_intra_threads(threads))
+            .map_err(|e| {
+                GlinerError::InitializationError(format!("Error building session: {}", e))
+            })?
+            .commit_from_file(&model_path)
+            .map_err(|e| GlinerError::file("loading", model_path.as_ref(), e))?;
 
-        ONNXWrapper {
-            session: session,
+        Ok(ONNXWrapper {
+            session,
             // settings,
-        }
+        })
     }
 
     // pub fn init(&mut self) -> Result<()> {
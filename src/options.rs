@@ -0,0 +1,118 @@
+use crate::decoder::SelectionStrategy;
+use crate::error::{GlinerError, Result};
+use crate::labels::LabelDefinition;
+use crate::offsets::OffsetUnit;
+use crate::postprocess::PostProcessing;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Decoding options of one inference call. Serializable so that thresholds
+// tuned for a model can be shipped next to it as a JSON profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InferenceOptions {
+    // Threshold of labels without an entry in `label_thresholds`.
+    pub threshold: f32,
+    pub label_thresholds: HashMap<String, f32>,
+    pub flat_ner: bool,
+    pub multi_label: bool,
+    // How overlapping spans are resolved.
+    pub selection: SelectionStrategy,
+    // When set, every returned span also carries the scores of its `top_k` best
+    // labels, whether or not they pass their threshold.
+    pub top_k: Option<usize>,
+    // Rules applied to the entities of each text after overlap resolution.
+    pub post_processing: PostProcessing,
+    // Prompts of labels, keyed by the label name returned in results. Labels
+    // without an entry are prompted with their name.
+    pub labels: HashMap<String, LabelDefinition>,
+    // Unit of the offsets set next to byte offsets on every entity.
+    pub offset_unit: OffsetUnit,
+}
+
+impl Default for InferenceOptions {
+    fn default() -> Self {
+        InferenceOptions {
+            threshold: 0.5,
+            label_thresholds: HashMap::new(),
+            flat_ner: true,
+            multi_label: false,
+            selection: SelectionStrategy::Greedy,
+            top_k: None,
+            post_processing: PostProcessing::default(),
+            labels: HashMap::new(),
+            offset_unit: OffsetUnit::Bytes,
+        }
+    }
+}
+
+impl InferenceOptions {
+    pub fn new(flat_ner: bool, threshold: f32) -> Self {
+        InferenceOptions {
+            threshold,
+            flat_ner,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_label_threshold(mut self, label: &str, threshold: f32) -> Self {
+        self.label_thresholds.insert(label.to_string(), threshold);
+        self
+    }
+
+    pub fn with_label_definition(mut self, label: &str, definition: LabelDefinition) -> Self {
+        self.labels.insert(label.to_string(), definition);
+        self
+    }
+
+    pub fn threshold_for(&self, label: &str) -> f32 {
+        self.label_thresholds
+            .get(label)
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+
+    // Thresholds of `entities`, indexed by class id.
+    pub fn thresholds(&self, entities: &[&str]) -> Vec<f32> {
+        entities.iter().map(|ent| self.threshold_for(ent)).collect()
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content =
+            std::fs::read_to_string(path).map_err(|e| GlinerError::file("reading", path, e))?;
+        serde_json::from_str(&content).map_err(|e| GlinerError::file("parsing", path, e))
+    }
+
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| GlinerError::file("writing", path, e))?;
+        std::fs::write(path, content).map_err(|e| GlinerError::file("writing", path, e))
+    }
+}
+
+// A text along with its own labels and options, for batches whose texts do
+// not share a schema.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InferenceItem {
+    pub text: String,
+    pub labels: Vec<String>,
+    pub options: InferenceOptions,
+}
+
+impl InferenceItem {
+    pub fn new(text: &str, labels: &[&str], options: InferenceOptions) -> Self {
+        InferenceItem {
+            text: text.to_string(),
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            options,
+        }
+    }
+
+    pub fn labels(&self) -> Vec<&str> {
+        self.labels.iter().map(String::as_str).collect()
+    }
+}
@@ -0,0 +1,178 @@
+use crate::types::{EntityResult, InferenceResultSingle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Stopwords suited to `PostProcessing::stopwords` for English text.
+pub const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "of", "on", "or",
+    "the", "to", "with",
+];
+
+// Rules applied to the entities of each text after overlap resolution. Every
+// rule is off by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostProcessing {
+    // Removes punctuation from both ends of spans, e.g. `"Al Nassr,"`.
+    pub trim_punctuation: bool,
+    // Punctuation left in place by `trim_punctuation`, such as "." to keep
+    // abbreviations like "U.S." whole.
+    pub keep_punctuation: String,
+    // Words removed from both ends of spans, compared case-insensitively, e.g.
+    // "and" in `"Al Nassr and"`.
+    pub stopwords: Vec<String>,
+    // Merges spans with the same label separated only by whitespace or
+    // hyphens, e.g. "New" and "York".
+    pub merge_adjacent: bool,
+    // Per-label bounds on the number of characters of a span. Shorter or
+    // longer spans are dropped.
+    pub min_chars: HashMap<String, usize>,
+    pub max_chars: HashMap<String, usize>,
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(
+            c,
+            '\u{2018}'..='\u{201f}' | '\u{2010}'..='\u{2015}' | '…' | '«' | '»' | '、' | '。' | '，'
+        )
+}
+
+fn is_joiner(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '-' | '\u{2010}'..='\u{2013}')
+}
+
+impl PostProcessing {
+    pub fn is_noop(&self) -> bool {
+        !self.trim_punctuation
+            && self.stopwords.is_empty()
+            && !self.merge_adjacent
+            && self.min_chars.is_empty()
+            && self.max_chars.is_empty()
+    }
+
+    // Applies the rules to the entities of every text, `results[i]` being the
+    // entities found in `texts[i]`.
+    pub fn apply_all(&self, texts: &[String], results: &mut [InferenceResultSingle]) {
+        if self.is_noop() {
+            return;
+        }
+        for (result, text) in results.iter_mut().zip(texts) {
+            result.entities = self.apply(text, std::mem::take(&mut result.entities));
+        }
+    }
+
+    // Trims, merges and filters `entities` of `text`, keeping `start`, `end`
+    // and `span_text` consistent. Entities trimmed down to nothing are dropped.
+    // A merged entity takes the lowest score of its parts. Entities whose
+    // offsets do not fall on character boundaries of `text` are only filtered.
+    pub fn apply(&self, text: &str, entities: Vec<EntityResult>) -> Vec<EntityResult> {
+        let mut entities: Vec<EntityResult> = entities
+            .into_iter()
+            .filter_map(|mut entity| {
+                if text.get(entity.start..entity.end).is_none() {
+                    return Some(entity);
+                }
+                let (start, end) = self.trim(text, entity.start, entity.end);
+                if start >= end {
+                    return None;
+                }
+                if (start, end) != (entity.start, entity.end) {
+                    entity.start = start;
+                    entity.end = end;
+                    entity.span_text = text[start..end].to_string();
+                    entity.label_scores.clear();
+                }
+                Some(entity)
+            })
+            .collect();
+
+        if self.merge_adjacent {
+            entities = self.merge(text, entities);
+        }
+
+        entities.retain(|entity| {
+            let chars = entity.span_text.chars().count();
+            self.min_chars
+                .get(&entity.label)
+                .is_none_or(|&min| chars >= min)
+                && self
+                    .max_chars
+                    .get(&entity.label)
+                    .is_none_or(|&max| chars <= max)
+        });
+        entities
+    }
+
+    fn is_stopword(&self, word: &str) -> bool {
+        self.stopwords
+            .iter()
+            .any(|stopword| stopword.eq_ignore_ascii_case(word))
+    }
+
+    // Byte range left of `start..end` once whitespace, punctuation and
+    // stopwords are removed from both ends.
+    fn trim(&self, text: &str, mut start: usize, mut end: usize) -> (usize, usize) {
+        let trimmed = |c: char| {
+            c.is_whitespace()
+                || (self.trim_punctuation
+                    && is_punctuation(c)
+                    && !self.keep_punctuation.contains(c))
+        };
+
+        loop {
+            let before = (start, end);
+
+            let span = text[start..end].trim_start_matches(trimmed);
+            start = end - span.len();
+            let span = span.trim_end_matches(trimmed);
+            end = start + span.len();
+
+            if let Some(first) = span.split_whitespace().next() {
+                if self.is_stopword(first) {
+                    start += first.len();
+                }
+            }
+            let span = &text[start..end];
+            if let Some(last) = span.split_whitespace().next_back() {
+                if self.is_stopword(last) {
+                    end -= last.len();
+                }
+            }
+
+            if (start, end) == before || start >= end {
+                return (start, end);
+            }
+        }
+    }
+
+    fn merge(&self, text: &str, mut entities: Vec<EntityResult>) -> Vec<EntityResult> {
+        entities.sort_by_key(|entity| (entity.start, entity.end));
+
+        let mut merged: Vec<EntityResult> = Vec::with_capacity(entities.len());
+        // Position in `merged` of the last entity of each label.
+        let mut last_of_label: HashMap<String, usize> = HashMap::new();
+        for entity in entities {
+            let joined = last_of_label.get(&entity.label).is_some_and(|&id| {
+                let previous = &merged[id];
+                previous.end <= entity.start
+                    && text.get(previous.start..entity.end).is_some()
+                    && text
+                        .get(previous.end..entity.start)
+                        .is_some_and(|gap| gap.chars().all(is_joiner))
+            });
+
+            if joined {
+                let previous = &mut merged[last_of_label[&entity.label]];
+                previous.end = entity.end;
+                previous.span_text = text[previous.start..previous.end].to_string();
+                previous.score = previous.score.min(entity.score);
+                previous.label_scores.clear();
+            } else {
+                last_of_label.insert(entity.label.clone(), merged.len());
+                merged.push(entity);
+            }
+        }
+        merged
+    }
+}
@@ -1,15 +1,66 @@
-use ndarray::{Array1, Array2, Array3};
+use ndarray::{Array2, Array3};
 use ort::{SessionInputValue, Value};
 use regex::Regex;
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
 use tokenizers::Tokenizer;
 
-use ndarray::{Array, ArrayD, Axis, IxDyn};
+use crate::config::ModelConfig;
+use crate::error::{GlinerError, Result};
+use tracing::warn;
+
+// Session inputs of a batch, followed by the class mapping, words, word
+// boundaries and word counts needed to decode the model output.
+pub type PreparedBatch<'a> = (
+    Vec<(Cow<'a, str>, SessionInputValue<'a>)>,
+    // Class names of each row, by position in its prompt.
+    Vec<HashMap<usize, String>>,
+    Vec<Vec<String>>,
+    Vec<Vec<usize>>,
+    Vec<Vec<usize>>,
+    Vec<usize>,
+);
+
+// Words of a text along with their start and end byte offsets, as returned by
+// `Processor::tokenize_text`.
+pub type Words = (Vec<String>, Vec<usize>, Vec<usize>);
+
+// Text of words split by an external tokenizer, joined with single spaces,
+// along with the words and their byte offsets in that text.
+pub fn join_words(words: &[String]) -> (String, Words) {
+    let mut text = String::new();
+    let mut words_start_idx = Vec::with_capacity(words.len());
+    let mut words_end_idx = Vec::with_capacity(words.len());
+    for (id, word) in words.iter().enumerate() {
+        if id > 0 {
+            text.push(' ');
+        }
+        words_start_idx.push(text.len());
+        text.push_str(word);
+        words_end_idx.push(text.len());
+    }
+    (text, (words.to_vec(), words_start_idx, words_end_idx))
+}
+
+// Splits a text into words, returned with their `(start, end)` byte offsets.
+// Spans can only start and end on word boundaries.
+pub trait WordSplitter: Send + Sync {
+    fn split(&self, text: &str) -> Vec<(String, usize, usize)>;
+}
 
 pub struct WhitespaceTokenSplitter {
     whitespace_pattern: Regex,
 }
 
+impl Default for WhitespaceTokenSplitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WhitespaceTokenSplitter {
     pub fn new() -> Self {
         WhitespaceTokenSplitter {
@@ -24,19 +75,358 @@ impl WhitespaceTokenSplitter {
     }
 }
 
+impl WordSplitter for WhitespaceTokenSplitter {
+    fn split(&self, text: &str) -> Vec<(String, usize, usize)> {
+        self.call(text).collect()
+    }
+}
+
+// Splits on every match of a user supplied pattern.
+pub struct RegexTokenSplitter {
+    pattern: Regex,
+}
+
+impl RegexTokenSplitter {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Ok(RegexTokenSplitter {
+            pattern: Regex::new(pattern).map_err(|e| {
+                GlinerError::InitializationError(format!(
+                    "Invalid words splitter pattern {}: {}",
+                    pattern, e
+                ))
+            })?,
+        })
+    }
+}
+
+impl WordSplitter for RegexTokenSplitter {
+    fn split(&self, text: &str) -> Vec<(String, usize, usize)> {
+        self.pattern
+            .find_iter(text)
+            .map(|m| (m.as_str().to_string(), m.start(), m.end()))
+            .collect()
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF // Hiragana, Katakana
+        | 0x31F0..=0x31FF // Katakana phonetic extensions
+        | 0x3400..=0x4DBF // CJK extension A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+        | 0xFF66..=0xFF9D // Halfwidth Katakana
+        | 0x20000..=0x2FA1F // CJK extensions B-F and supplement
+    )
+}
+
+// Same as `WhitespaceTokenSplitter`, except that Chinese and Japanese
+// characters, which are not separated by spaces, each form a word of their own.
+#[derive(Default)]
+pub struct CjkTokenSplitter {
+    whitespace: WhitespaceTokenSplitter,
+}
+
+impl CjkTokenSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl WordSplitter for CjkTokenSplitter {
+    fn split(&self, text: &str) -> Vec<(String, usize, usize)> {
+        let mut words = Vec::new();
+
+        for (word, start, end) in self.whitespace.call(text) {
+            if !word.chars().any(is_cjk) {
+                words.push((word, start, end));
+                continue;
+            }
+
+            let mut run_start: Option<usize> = None;
+            for (offset, c) in word.char_indices() {
+                let position = start + offset;
+                if is_cjk(c) {
+                    if let Some(run) = run_start.take() {
+                        words.push((text[run..position].to_string(), run, position));
+                    }
+                    let char_end = position + c.len_utf8();
+                    words.push((c.to_string(), position, char_end));
+                } else if run_start.is_none() {
+                    run_start = Some(position);
+                }
+            }
+            if let Some(run) = run_start {
+                words.push((text[run..end].to_string(), run, end));
+            }
+        }
+
+        words
+    }
+}
+
+// Builds the word splitter named by `words_splitter_type`. Segmenters GLiNER
+// uses for Chinese and Japanese (jieba, mecab, janome) map to the character
+// level CJK splitter; unknown types fall back to whitespace splitting.
+pub fn words_splitter_from_config(config: &ModelConfig) -> Result<Box<dyn WordSplitter>> {
+    Ok(match config.words_splitter_type.as_str() {
+        "whitespace" => Box::new(WhitespaceTokenSplitter::new()),
+        "cjk" | "char" | "jieba" | "mecab" | "janome" => Box::new(CjkTokenSplitter::new()),
+        "regex" => {
+            let pattern = config.words_splitter_pattern.as_deref().ok_or_else(|| {
+                GlinerError::InitializationError(
+                    "words_splitter_type \"regex\" requires words_splitter_pattern".to_string(),
+                )
+            })?;
+            Box::new(RegexTokenSplitter::new(pattern)?)
+        }
+        other => {
+            warn!(
+                "Unsupported words_splitter_type {:?}, falling back to whitespace",
+                other
+            );
+            Box::new(WhitespaceTokenSplitter::new())
+        }
+    })
+}
+
+// Splits a text into sentences, returned as `(start, end)` byte ranges.
+pub trait SentenceSplitter: Send + Sync {
+    fn split(&self, text: &str) -> Vec<(usize, usize)>;
+}
+
+const DEFAULT_ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "mt", "vs", "etc", "e.g", "i.e", "cf", "al",
+    "inc", "ltd", "co", "corp", "dept", "no", "nos", "fig", "approx", "est", "gen", "gov", "sen",
+    "rep", "rev", "hon", "jan", "feb", "mar", "apr", "jun", "jul", "aug", "sep", "sept", "oct",
+    "nov", "dec", "u.s", "u.k", "a.m", "p.m", "ph.d", "art", "sec", "para", "vol", "pp",
+];
+
+// Rule-based sentence splitter. Breaks after terminal punctuation (and any
+// closing quotes or brackets) when the next word looks like a sentence start,
+// unless the period ends a known abbreviation or an initial. Blank lines and
+// lines starting with a bullet marker always start a new sentence.
+pub struct RuleSentenceSplitter {
+    abbreviations: HashSet<String>,
+}
+
+impl Default for RuleSentenceSplitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RuleSentenceSplitter {
+    pub fn new() -> Self {
+        Self::with_abbreviations(DEFAULT_ABBREVIATIONS)
+    }
+
+    pub fn with_abbreviations(abbreviations: &[&str]) -> Self {
+        RuleSentenceSplitter {
+            abbreviations: abbreviations
+                .iter()
+                .map(|abbr| abbr.trim_end_matches('.').to_lowercase())
+                .collect(),
+        }
+    }
+
+    fn is_abbreviation(&self, text: &str, period: usize) -> bool {
+        let word_start = text[..period]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '.')
+            .last()
+            .map_or(period, |(i, _)| i);
+        let word = &text[word_start..period];
+        let mut chars = word.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => false,
+            // Single letter initials such as "J. R. R. Tolkien".
+            (Some(c), None) => c.is_alphabetic(),
+            _ => self.abbreviations.contains(&word.to_lowercase()),
+        }
+    }
+}
+
+fn is_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…' | '。' | '！' | '？')
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(c, '"' | '\'' | '”' | '’' | '»' | ')' | ']' | '」' | '』')
+}
+
+fn starts_sentence(c: char) -> bool {
+    c.is_uppercase()
+        || c.is_numeric()
+        || matches!(
+            c,
+            '"' | '\'' | '“' | '‘' | '«' | '(' | '[' | '-' | '*' | '•'
+        )
+        || (c.is_alphabetic() && !c.is_lowercase())
+}
+
+fn is_bullet_at(chars: &[(usize, char)], i: usize) -> bool {
+    let followed_by_space = |j: usize| chars.get(j).is_none_or(|(_, c)| c.is_whitespace());
+    match chars[i].1 {
+        '-' | '*' | '•' | '·' | '–' | '▪' | '◦' => followed_by_space(i + 1),
+        c if c.is_ascii_digit() => {
+            let mut j = i;
+            while j < chars.len() && chars[j].1.is_ascii_digit() {
+                j += 1;
+            }
+            j < chars.len() && matches!(chars[j].1, '.' | ')') && followed_by_space(j + 1)
+        }
+        _ => false,
+    }
+}
+
+fn push_sentence(text: &str, start: usize, end: usize, sentences: &mut Vec<(usize, usize)>) {
+    let slice = &text[start..end];
+    let trimmed_start = start + (slice.len() - slice.trim_start().len());
+    let trimmed_end = start + slice.trim_end().len();
+    if trimmed_start < trimmed_end {
+        sentences.push((trimmed_start, trimmed_end));
+    }
+}
+
+impl SentenceSplitter for RuleSentenceSplitter {
+    fn split(&self, text: &str) -> Vec<(usize, usize)> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let byte_at = |i: usize| chars.get(i).map_or(text.len(), |&(pos, _)| pos);
+        let mut sentences = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let (pos, c) = chars[i];
+
+            if c == '\n' {
+                let mut j = i;
+                let mut newlines = 0;
+                while j < chars.len() && chars[j].1.is_whitespace() {
+                    if chars[j].1 == '\n' {
+                        newlines += 1;
+                    }
+                    j += 1;
+                }
+                if newlines >= 2 || (j < chars.len() && is_bullet_at(&chars, j)) {
+                    push_sentence(text, start, pos, &mut sentences);
+                    start = byte_at(j);
+                }
+                i = j;
+                continue;
+            }
+
+            if is_terminator(c) {
+                let mut j = i + 1;
+                while j < chars.len() && is_terminator(chars[j].1) {
+                    j += 1;
+                }
+                while j < chars.len() && is_closing(chars[j].1) {
+                    j += 1;
+                }
+
+                let boundary = if j >= chars.len() || matches!(c, '。' | '！' | '？') {
+                    true
+                } else if chars[j].1.is_whitespace() {
+                    let mut k = j;
+                    while k < chars.len() && chars[k].1.is_whitespace() {
+                        k += 1;
+                    }
+                    k >= chars.len() || starts_sentence(chars[k].1)
+                } else {
+                    false
+                };
+
+                if boundary && !(c == '.' && j == i + 1 && self.is_abbreviation(text, pos)) {
+                    let end = byte_at(j);
+                    push_sentence(text, start, end, &mut sentences);
+                    start = end;
+                }
+                i = j;
+                continue;
+            }
+
+            i += 1;
+        }
+
+        push_sentence(text, start, text.len(), &mut sentences);
+        sentences
+    }
+}
+
+// Resolves the ids the tokenizer's post-processor puts before and after a
+// sequence by encoding a probe word with special tokens enabled.
+fn special_token_ids(tokenizer: &Tokenizer) -> Result<(Option<u32>, Option<u32>)> {
+    let encoding = tokenizer.encode("a", true).map_err(|e| {
+        GlinerError::TokenizerError(format!("Error encoding with special tokens: {}", e))
+    })?;
+    let ids = encoding.get_ids();
+    let mask = encoding.get_special_tokens_mask();
+
+    let leading = mask.iter().take_while(|&&special| special == 1).count();
+    let trailing = mask
+        .iter()
+        .rev()
+        .take_while(|&&special| special == 1)
+        .count();
+    if leading < ids.len() && leading + trailing > 0 {
+        return Ok((
+            ids[..leading].first().copied(),
+            ids[ids.len() - trailing..].last().copied(),
+        ));
+    }
+
+    // Tokenizers without a post-processor: fall back to well-known names.
+    let find = |names: &[&str]| names.iter().find_map(|name| tokenizer.token_to_id(name));
+    let start = find(&["[CLS]", "<s>", "<cls>", "[BOS]"]);
+    let end = find(&["[SEP]", "</s>", "<sep>", "[EOS]"]);
+    if start.is_none() && end.is_none() {
+        return Err(GlinerError::InitializationError(
+            "Could not determine the start and end special tokens of the tokenizer".to_string(),
+        ));
+    }
+    Ok((start, end))
+}
+
 pub struct Processor {
-    config: HashMap<String, String>,
+    config: ModelConfig,
     tokenizer: Tokenizer,
-    words_splitter: WhitespaceTokenSplitter,
+    words_splitter: Box<dyn WordSplitter>,
+    // Special tokens the tokenizer wraps every sequence with, e.g. [CLS]/[SEP]
+    // for BERT-like vocabularies or <s>/</s> for RoBERTa-like ones.
+    start_token_id: Option<u32>,
+    end_token_id: Option<u32>,
 }
 
 impl Processor {
-    pub fn new(config: HashMap<String, String>, tokenizer: Tokenizer) -> Self {
-        Processor {
+    pub fn new(config: ModelConfig, tokenizer: Tokenizer) -> Result<Self> {
+        for token in [&config.ent_token, &config.sep_token] {
+            let ids = tokenizer.encode(token.as_str(), false).map_err(|e| {
+                GlinerError::TokenizerError(format!("Error encoding {}: {}", token, e))
+            })?;
+            let is_added = tokenizer
+                .token_to_id(token)
+                .is_some_and(|id| ids.get_ids() == [id]);
+            if !is_added {
+                return Err(GlinerError::InitializationError(format!(
+                    "Tokenizer does not encode {} as a single added token; it does not match a GLiNER model",
+                    token
+                )));
+            }
+        }
+
+        let (start_token_id, end_token_id) = special_token_ids(&tokenizer)?;
+        let words_splitter = words_splitter_from_config(&config)?;
+
+        Ok(Processor {
             config,
             tokenizer,
-            words_splitter: WhitespaceTokenSplitter::new(),
-        }
+            words_splitter,
+            start_token_id,
+            end_token_id,
+        })
     }
 
     pub fn tokenize_text(&self, text: &str) -> (Vec<String>, Vec<usize>, Vec<usize>) {
@@ -44,7 +434,7 @@ impl Processor {
         let mut words_start_idx = Vec::new();
         let mut words_end_idx = Vec::new();
 
-        for (token, start, end) in self.words_splitter.call(text) {
+        for (token, start, end) in self.words_splitter.split(text) {
             tokens.push(token);
             words_start_idx.push(start);
             words_end_idx.push(end);
@@ -53,6 +443,7 @@ impl Processor {
         (tokens, words_start_idx, words_end_idx)
     }
 
+    #[allow(clippy::type_complexity)]
     pub fn batch_tokenize_text(
         &self,
         texts: &[String],
@@ -87,24 +478,114 @@ impl Processor {
         (class_to_id, id_to_class)
     }
 
+    pub fn count_subwords(&self, word: &str) -> usize {
+        self.tokenizer
+            .encode(word, false)
+            .map(|encoding| encoding.get_ids().len())
+            .unwrap_or(0)
+    }
+
+    // Leaves out words that encode to no subword, e.g. line breaks given as
+    // pre-tokenized words, which would get no `words_mask` index while still
+    // counting in `text_lengths`. Words passed to `BatchProcessor::prepare_rows`
+    // must go through it first. Spans are decoded to byte offsets, so entities
+    // still map back to the original words.
+    pub fn drop_empty_words(&self, words: &Words) -> Words {
+        let (tokens, words_start_idx, words_end_idx) = words;
+        let kept: Vec<usize> = (0..tokens.len())
+            .filter(|&id| self.count_subwords(&tokens[id]) > 0)
+            .collect();
+        (
+            kept.iter().map(|&id| tokens[id].clone()).collect(),
+            kept.iter().map(|&id| words_start_idx[id]).collect(),
+            kept.iter().map(|&id| words_end_idx[id]).collect(),
+        )
+    }
+
+    fn special_token_count(&self) -> usize {
+        self.start_token_id.is_some() as usize + self.end_token_id.is_some() as usize
+    }
+
+    // Number of subword tokens taken by the label prompt, including the
+    // special tokens added around every sequence by `encode_inputs`.
+    pub fn prompt_token_count(&self, entities: &[&str]) -> usize {
+        let ent_token = self.count_subwords(&self.config.ent_token);
+        let labels: usize = entities.iter().map(|ent| self.count_subwords(ent)).sum();
+        ent_token * entities.len()
+            + labels
+            + self.count_subwords(&self.config.sep_token)
+            + self.special_token_count()
+    }
+
+    // Splits the label list into consecutive groups whose prompt fits in
+    // `max_prompt_tokens`. A label that does not fit on its own gets its own group.
+    pub fn partition_labels(
+        &self,
+        entities: &[&str],
+        max_prompt_tokens: usize,
+    ) -> Vec<Range<usize>> {
+        let ent_token = self.count_subwords(&self.config.ent_token);
+        let fixed = self.count_subwords(&self.config.sep_token) + self.special_token_count();
+        let mut groups = Vec::new();
+        let mut start = 0;
+        let mut used = fixed;
+
+        for (index, ent) in entities.iter().enumerate() {
+            let cost = ent_token + self.count_subwords(ent);
+            if index > start && used + cost > max_prompt_tokens {
+                groups.push(start..index);
+                start = index;
+                used = fixed;
+            }
+            used += cost;
+        }
+        if start < entities.len() {
+            groups.push(start..entities.len());
+        }
+
+        groups
+    }
+
+    // Groups of labels that are prompted together. The whole list forms a single
+    // group unless its prompt takes more than half of `max_len`, in which case it
+    // is split so that enough of the context is left for the text.
+    #[allow(clippy::single_range_in_vec_init)]
+    pub fn label_groups(&self, entities: &[&str], max_len: usize) -> Vec<Range<usize>> {
+        let max_prompt_tokens = max_len / 2;
+        if self.prompt_token_count(entities) <= max_prompt_tokens {
+            return vec![0..entities.len()];
+        }
+        self.partition_labels(entities, max_prompt_tokens)
+    }
     pub fn prepare_text_inputs(
         &self,
         tokens: &[Vec<String>],
         entities: &[&str],
+    ) -> (Vec<Vec<String>>, Vec<usize>, Vec<usize>) {
+        self.prepare_row_text_inputs(tokens, &vec![entities; tokens.len()])
+    }
+
+    // Same as `prepare_text_inputs` with a label list per text. Prompts of
+    // different lengths are padded along with the texts, and the model pads
+    // the class dimension of its output to the longest list.
+    pub fn prepare_row_text_inputs(
+        &self,
+        tokens: &[Vec<String>],
+        entities: &[&[&str]],
     ) -> (Vec<Vec<String>>, Vec<usize>, Vec<usize>) {
         let mut input_texts = Vec::new();
         let mut prompt_lengths = Vec::new();
         let mut text_lengths = Vec::new();
 
-        for text in tokens {
+        for (text, row_entities) in tokens.iter().zip(entities) {
             text_lengths.push(text.len());
 
             let mut input_text = Vec::new();
-            for ent in entities {
-                input_text.push("<<ENT>>".to_string());
+            for ent in row_entities.iter() {
+                input_text.push(self.config.ent_token.clone());
                 input_text.push(ent.to_string());
             }
-            input_text.push("<<SEP>>".to_string());
+            input_text.push(self.config.sep_token.clone());
             let prompt_length = input_text.len();
             prompt_lengths.push(prompt_length);
             input_text.extend_from_slice(text);
@@ -114,24 +595,35 @@ impl Processor {
         (input_texts, text_lengths, prompt_lengths)
     }
 
+    #[allow(clippy::type_complexity)]
     pub fn encode_inputs(
         &self,
         texts: &[Vec<String>],
         prompt_lengths: Option<&[usize]>,
-    ) -> (Vec<Vec<u32>>, Vec<Vec<usize>>, Vec<Vec<usize>>) {
+    ) -> Result<(Vec<Vec<u32>>, Vec<Vec<usize>>, Vec<Vec<usize>>)> {
         let mut words_masks = Vec::new();
         let mut inputs_ids = Vec::new();
         let mut attention_masks = Vec::new();
 
         for (id, text) in texts.iter().enumerate() {
-            let prompt_length = prompt_lengths.map_or(0, |pl| pl[id]);
-            let mut words_mask = vec![0];
-            let mut input_ids = vec![1];
-            let mut attention_mask = vec![1];
+            let prompt_length = prompt_lengths
+                .and_then(|pl| pl.get(id))
+                .copied()
+                .unwrap_or(0);
+            let mut words_mask = Vec::new();
+            let mut input_ids = Vec::new();
+            let mut attention_mask = Vec::new();
+            if let Some(start_token_id) = self.start_token_id {
+                words_mask.push(0);
+                input_ids.push(start_token_id);
+                attention_mask.push(1);
+            }
 
             let mut c = 1;
             for (word_id, word) in text.iter().enumerate() {
-                let word_tokens = self.tokenizer.encode(word.as_str(), false).unwrap();
+                let word_tokens = self.tokenizer.encode(word.as_str(), false).map_err(|e| {
+                    GlinerError::TokenizerError(format!("Error encoding {}: {}", word, e))
+                })?;
                 let word_tokens = word_tokens.get_ids();
                 for (token_id, &token) in word_tokens.iter().enumerate() {
                     attention_mask.push(1);
@@ -146,16 +638,104 @@ impl Processor {
                     input_ids.push(token);
                 }
             }
-            words_mask.push(0);
-            input_ids.push(128003);
-            attention_mask.push(1);
+            if let Some(end_token_id) = self.end_token_id {
+                words_mask.push(0);
+                input_ids.push(end_token_id);
+                attention_mask.push(1);
+            }
 
             words_masks.push(words_mask);
             inputs_ids.push(input_ids);
             attention_masks.push(attention_mask);
         }
 
-        (inputs_ids, attention_masks, words_masks)
+        Ok((inputs_ids, attention_masks, words_masks))
+    }
+
+    // Builds the encoder inputs shared by every model kind from the words of
+    // each text and its labels: `input_ids`, `attention_mask`, `words_mask`
+    // and `text_lengths`.
+    pub fn prepare_encoder_batch(
+        &self,
+        words: &[Words],
+        entities: &[&[&str]],
+    ) -> Result<PreparedBatch<'_>> {
+        if words.len() != entities.len() {
+            return Err(GlinerError::InputShapeError(format!(
+                "{} label lists were given for {} texts",
+                entities.len(),
+                words.len()
+            )));
+        }
+        let mut batch_tokens = Vec::with_capacity(words.len());
+        let mut batch_words_start_idx = Vec::with_capacity(words.len());
+        let mut batch_words_end_idx = Vec::with_capacity(words.len());
+        for (tokens, words_start_idx, words_end_idx) in words {
+            batch_tokens.push(tokens.clone());
+            batch_words_start_idx.push(words_start_idx.clone());
+            batch_words_end_idx.push(words_end_idx.clone());
+        }
+        let id_to_class = entities
+            .iter()
+            .map(|row_entities| self.create_mappings(row_entities).1)
+            .collect();
+        let (input_tokens, text_lengths, prompt_lengths) =
+            self.prepare_row_text_inputs(&batch_tokens, entities);
+
+        let (mut inputs_ids, mut attention_masks, mut words_masks) =
+            self.encode_inputs(&input_tokens, Some(&prompt_lengths))?;
+
+        inputs_ids = pad_array(&inputs_ids);
+        let batch_size = inputs_ids.len();
+        attention_masks = pad_array(&attention_masks);
+        words_masks = pad_array(&words_masks);
+
+        let input_ids_insert: Array2<i64> = Array2::from_shape_vec(
+            (batch_size, row_length(&inputs_ids)),
+            inputs_ids
+                .iter()
+                .flat_map(|row| row.iter().map(|&id| id as i64))
+                .collect(),
+        )?;
+        let attention_masks_insert: Array2<i64> = Array2::from_shape_vec(
+            (batch_size, row_length(&attention_masks)),
+            attention_masks
+                .iter()
+                .flat_map(|row| row.iter().map(|&id| id as i64))
+                .collect(),
+        )?;
+        let words_masks_insert: Array2<i64> = Array2::from_shape_vec(
+            (batch_size, row_length(&words_masks)),
+            words_masks
+                .iter()
+                .flat_map(|row| row.iter().map(|&id| id as i64))
+                .collect(),
+        )?;
+        let text_lengths_insert: Array2<i64> = Array2::from_shape_vec(
+            (batch_size, 1),
+            text_lengths.iter().map(|&len| len as i64).collect(),
+        )?;
+
+        let input_ids = Value::from_array(input_ids_insert).map_err(shape_error)?;
+        let attention_mask = Value::from_array(attention_masks_insert).map_err(shape_error)?;
+        let words_mask = Value::from_array(words_masks_insert).map_err(shape_error)?;
+        let text_lengths_value = Value::from_array(text_lengths_insert).map_err(shape_error)?;
+        let result = ort::inputs![
+            "input_ids" => input_ids,
+            "attention_mask" => attention_mask,
+            "words_mask" => words_mask,
+            "text_lengths" => text_lengths_value,
+        ]
+        .map_err(shape_error)?;
+
+        Ok((
+            result,
+            id_to_class,
+            batch_tokens,
+            batch_words_start_idx,
+            batch_words_end_idx,
+            text_lengths,
+        ))
     }
 
     // pub fn pad_array<T: Clone + Default>(&self, arr: &mut Vec<Vec<T>>, dimensions: usize) {
@@ -204,15 +784,36 @@ impl Processor {
     // }
 }
 
+// Builds the session inputs of a batch for one kind of GLiNER model.
+pub trait BatchProcessor {
+    fn processor(&self) -> &Processor;
+
+    // Same as `prepare_words` with a label list per text.
+    fn prepare_rows(&self, words: &[Words], entities: &[&[&str]]) -> Result<PreparedBatch<'_>>;
+
+    // Same as `prepare_batch` for texts already split into words.
+    fn prepare_words(&self, words: &[Words], entities: &[&str]) -> Result<PreparedBatch<'_>> {
+        self.prepare_rows(words, &vec![entities; words.len()])
+    }
+
+    fn prepare_batch(&self, texts: &[String], entities: &[&str]) -> Result<PreparedBatch<'_>> {
+        let words: Vec<Words> = texts
+            .iter()
+            .map(|text| self.processor().tokenize_text(text))
+            .collect();
+        self.prepare_words(&words, entities)
+    }
+}
+
 pub struct SpanProcessor {
     processor: Processor,
 }
 
 impl SpanProcessor {
-    pub fn new(config: HashMap<String, String>, tokenizer: Tokenizer) -> Self {
-        SpanProcessor {
-            processor: Processor::new(config, tokenizer),
-        }
+    pub fn new(config: ModelConfig, tokenizer: Tokenizer) -> Result<Self> {
+        Ok(SpanProcessor {
+            processor: Processor::new(config, tokenizer)?,
+        })
     }
 
     pub fn prepare_spans(
@@ -242,39 +843,24 @@ impl SpanProcessor {
 
         (span_idxs, span_masks)
     }
+}
 
-    pub fn prepare_batch(
-        &self,
-        texts: &[String],
-        entities: &[&str],
-    ) -> (
-        Vec<(Cow<'_, str>, SessionInputValue<'_>)>,
-        HashMap<usize, String>,
-        Vec<Vec<String>>,
-        Vec<Vec<usize>>,
-        Vec<Vec<usize>>,
-        Vec<usize>,
-    ) {
-        let (batch_tokens, batch_words_start_idx, batch_words_end_idx) =
-            self.processor.batch_tokenize_text(texts);
-        let (_, id_to_class) = self.processor.create_mappings(entities);
-        let (input_tokens, text_lengths, prompt_lengths) =
-            self.processor.prepare_text_inputs(&batch_tokens, entities);
-
-        let (mut inputs_ids, mut attention_masks, mut words_masks) = self
-            .processor
-            .encode_inputs(&input_tokens, Some(&prompt_lengths));
-
-        inputs_ids = pad_array(&inputs_ids);
-        let batch_size = inputs_ids.len();
-        attention_masks = pad_array(&attention_masks);
-        words_masks = pad_array(&words_masks);
+impl BatchProcessor for SpanProcessor {
+    fn processor(&self) -> &Processor {
+        &self.processor
+    }
 
-        // self.processor.pad_array(&mut inputs_ids, 2);
-        // self.processor.pad_array(&mut attention_masks, 2);
-        // self.processor.pad_array(&mut words_masks, 2);
+    fn prepare_rows(&self, words: &[Words], entities: &[&[&str]]) -> Result<PreparedBatch<'_>> {
+        let (
+            mut result,
+            id_to_class,
+            batch_tokens,
+            batch_words_start_idx,
+            batch_words_end_idx,
+            text_lengths,
+        ) = self.processor.prepare_encoder_batch(words, entities)?;
 
-        let max_width = self.processor.config["max_width"].parse().unwrap_or(12);
+        let max_width = self.processor.config.max_width;
         let (mut span_idxs, mut span_masks) = self.prepare_spans(&batch_tokens, max_width);
 
         // self.processor.pad_array(&mut span_idxs, 3);
@@ -282,53 +868,8 @@ impl SpanProcessor {
         span_idxs = pad_array_3d(&span_idxs);
         span_masks = pad_array(&span_masks);
 
-        let input_ids_insert: Array2<i64> = Array2::from_shape_vec(
-            (inputs_ids.len(), inputs_ids[0].len()),
-            inputs_ids
-                .iter()
-                .flat_map(|row| row.iter().map(|&id| id as i64))
-                .collect(),
-        )
-        .unwrap();
-        let attention_masks_insert: Array2<i64> = Array2::from_shape_vec(
-            (attention_masks.len(), attention_masks[0].len()),
-            attention_masks
-                .iter()
-                .flat_map(|row| row.iter().map(|&id| id as i64))
-                .collect(),
-        )
-        .unwrap();
-        let words_masks_insert: Array2<i64> = Array2::from_shape_vec(
-            (words_masks.len(), words_masks[0].len()),
-            words_masks
-                .iter()
-                .flat_map(|row| row.iter().map(|&id| id as i64))
-                .collect(),
-        )
-        .unwrap();
-        // let text_lengths_insert: Array1<i64> =
-        //     Array1::from_vec(text_lengths.iter().map(|&len| len as i64).collect());
-        let text_lengths_insert: Array2<i64> = Array2::from_shape_vec(
-            (batch_size, 1),
-            text_lengths.iter().map(|&len| len as i64).collect(),
-        )
-        .expect("Failed to reshape text_lengths");
-
-        // println!("Span_idxs {:?}", span_idxs);
-        // println!(
-        //     "Span_idxs dimensions: {} x {} x {}",
-        //     span_idxs.len(),
-        //     span_idxs[0].len(),
-        //     span_idxs[0][0].len()
-        // );
-        // let total_elements: usize = span_idxs
-        //     .iter()
-        //     .flat_map(|row| row.iter().map(|sub_row| sub_row.len()))
-        //     .sum();
-        // println!("Total elements in span_idxs: {}", total_elements);
-
         let span_idxs_insert: Array3<i64> = Array3::from_shape_vec(
-            (span_idxs.len(), span_idxs[0].len(), span_idxs[0][0].len()),
+            (span_idxs.len(), row_length(&span_idxs), 2),
             span_idxs
                 .iter()
                 .flat_map(|row| {
@@ -336,88 +877,57 @@ impl SpanProcessor {
                         .flat_map(|sub_row| sub_row.iter().map(|&id| id as i64))
                 })
                 .collect(),
-        )
-        .unwrap();
+        )?;
         let span_masks_insert: Array2<bool> = Array2::from_shape_vec(
-            (span_masks.len(), span_masks[0].len()),
+            (span_masks.len(), row_length(&span_masks)),
             span_masks
                 .iter()
-                .flat_map(|row| row.iter().map(|&id| id))
+                .flat_map(|row| row.iter().copied())
                 .collect(),
-        )
-        .unwrap();
-        // let id_to_class_insert: Array2<i64> = Array2::from_shape_vec(
-        //     (id_to_class.len(), 2),
-        //     id_to_class
-        //         .iter()
-        //         .flat_map(|(_, &id)| vec![id as i64, 0])
-        //         .collect(),
-        // )
-        // .unwrap();
-        // let batch_tokens_insert: Array2<i64> = Array2::from_shape_vec(
-        //     (batch_tokens.len(), batch_tokens[0].len()),
-        //     batch_tokens
-        //         .iter()
-        //         .flat_map(|row| row.iter().map(|&id| id as i64))
-        //         .collect(),
-        // )
-        // .unwrap();
-        // batch_words_start_idx = pad_array(&batch_words_start_idx);
-
-        // let batch_words_start_idx_insert: Array2<i64> = Array2::from_shape_vec(
-        //     (batch_words_start_idx.len(), batch_words_start_idx[0].len()),
-        //     batch_words_start_idx
-        //         .iter()
-        //         .flat_map(|row| row.iter().map(|&id| id as i64))
-        //         .collect(),
-        // )
-        // .unwrap();
-        // let batch_words_end_idx_insert: Array2<i64> = Array2::from_shape_vec(
-        //     (batch_words_end_idx.len(), batch_words_end_idx[0].len()),
-        //     batch_words_end_idx
-        //         .iter()
-        //         .flat_map(|row| row.iter().map(|&id| id as i64))
-        //         .collect(),
-        // )
-        // .unwrap();
-
-        let result = ort::inputs![
-            "input_ids" => Value::from_array(input_ids_insert.clone())?,
-            "attention_mask" => Value::from_array(attention_masks_insert.clone())?,
-            "words_mask" => Value::from_array(words_masks_insert.clone())?,
-            "text_lengths" => Value::from_array(text_lengths_insert.clone())?,
-            "span_idx" => Value::from_array(span_idxs_insert.clone())?,
-            "span_mask" => Value::from_array(span_masks_insert.clone())?,
-            // "id_to_class" => Value::from_array(id_to_class_insert.clone())?,
-            // "batch_tokens" => Value::from_array(batch_tokens_insert.clone())?,
-            // "batch_words_start_idx" => Value::from_array(batch_words_start_idx_insert.clone())?,
-            // "batch_words_end_idx" => Value::from_array(batch_words_end_idx_insert.clone())?,
-        ]
-        .unwrap();
-        // result.insert("inputs_ids".to_string(), inputs_ids);
-        // result.insert("attention_masks".to_string(), attention_masks);
-        // result.insert("words_masks".to_string(), words_masks);
-        // result.insert("text_lengths".to_string(), vec![text_lengths]);
-        // result.insert(
-        //     "span_idxs".to_string(),
-        //     span_idxs.into_iter().flatten().collect(),
-        // );
-        // let span_masks_usize: Vec<Vec<usize>> = span_masks
-        //     .into_iter()
-        //     .map(|v| v.into_iter().map(|b| b as usize).collect())
-        //     .collect();
-        // result.insert("span_masks".to_string(), span_masks_usize);
-        // Note: id_to_class, batch_tokens, batch_words_start_idx, and batch_words_end_idx are not included
-        // as they are not easily representable in the same structure. You may need to handle these separately.
-
-        (
+        )?;
+
+        let span_idx = Value::from_array(span_idxs_insert).map_err(shape_error)?;
+        let span_mask = Value::from_array(span_masks_insert).map_err(shape_error)?;
+        result.extend(
+            ort::inputs![
+                "span_idx" => span_idx,
+                "span_mask" => span_mask,
+            ]
+            .map_err(shape_error)?,
+        );
+
+        Ok((
             result,
             id_to_class,
             batch_tokens,
             batch_words_start_idx,
             batch_words_end_idx,
             text_lengths,
-        )
+        ))
+    }
+}
+
+// Processor for token-level models, which score every word as the start, end
+// or inside of an entity and take no span inputs.
+pub struct TokenProcessor {
+    processor: Processor,
+}
+
+impl TokenProcessor {
+    pub fn new(config: ModelConfig, tokenizer: Tokenizer) -> Result<Self> {
+        Ok(TokenProcessor {
+            processor: Processor::new(config, tokenizer)?,
+        })
+    }
+}
+
+impl BatchProcessor for TokenProcessor {
+    fn processor(&self) -> &Processor {
+        &self.processor
+    }
+
+    fn prepare_rows(&self, words: &[Words], entities: &[&[&str]]) -> Result<PreparedBatch<'_>> {
+        self.processor.prepare_encoder_batch(words, entities)
     }
 }
 
@@ -430,6 +940,16 @@ impl Padable for usize {}
 impl Padable for bool {}
 impl Padable for u32 {}
 
+// Tensor construction fails on malformed inputs, not in the session.
+fn shape_error(error: ort::Error) -> GlinerError {
+    GlinerError::InputShapeError(error.to_string())
+}
+
+// Length of the rows of a padded array, 0 for an empty one.
+fn row_length<T>(arr: &[Vec<T>]) -> usize {
+    arr.first().map_or(0, Vec::len)
+}
+
 pub fn pad_array<T: Padable>(arr: &[Vec<T>]) -> Vec<Vec<T>> {
     let max_length = arr.iter().map(|sub_arr| sub_arr.len()).max().unwrap_or(0);
 
@@ -444,10 +964,11 @@ pub fn pad_array<T: Padable>(arr: &[Vec<T>]) -> Vec<Vec<T>> {
 
 pub fn pad_array_3d<T: Padable>(arr: &[Vec<Vec<T>>]) -> Vec<Vec<Vec<T>>> {
     let max_length = arr.iter().map(|sub_arr| sub_arr.len()).max().unwrap_or(0);
+    // Taken from the first non-empty row, as leading rows may have no items.
     let inner_length = arr
-        .get(0)
-        .and_then(|first| first.get(0).map(|inner| inner.len()))
-        .unwrap_or(0);
+        .iter()
+        .find_map(|sub_arr| sub_arr.first())
+        .map_or(0, Vec::len);
 
     arr.iter()
         .map(|sub_arr| {
@@ -0,0 +1,144 @@
+use crate::error::{GlinerError, Result};
+use crate::redact::{replace_entities, Redaction};
+use crate::types::InferenceResultSingle;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+// A surface form and the placeholder standing for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pseudonym {
+    pub placeholder: String,
+    pub label: String,
+    pub value: String,
+}
+
+// Placeholders of the entities of one conversation, e.g. `PERSON_1` for every
+// occurrence of "John Smith", so that text can be sent to an external model
+// and its answer mapped back. Serializable to carry a conversation across
+// requests.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PseudonymSession {
+    pseudonyms: Vec<Pseudonym>,
+}
+
+// Placeholder prefix of a label: uppercase, with runs of other characters
+// turned into `_`, e.g. "email address" gives `EMAIL_ADDRESS`.
+fn prefix(label: &str) -> String {
+    let words: Vec<String> = label
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_uppercase)
+        .collect();
+    if words.is_empty() {
+        "ENTITY".to_string()
+    } else {
+        words.join("_")
+    }
+}
+
+// Prefix letters with separators removed, under which rehydration looks up
+// placeholders written with a different case or punctuation.
+fn compact(prefix: &str) -> String {
+    prefix
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+impl PseudonymSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pseudonyms(&self) -> &[Pseudonym] {
+        &self.pseudonyms
+    }
+
+    // Placeholder of `value`, assigning the next free one of `label` if the
+    // value was not seen before. A value keeps its first placeholder whatever
+    // label it is found with later.
+    pub fn placeholder(&mut self, label: &str, value: &str) -> String {
+        if let Some(pseudonym) = self.pseudonyms.iter().find(|p| p.value == value) {
+            return pseudonym.placeholder.clone();
+        }
+        let prefix = prefix(label);
+        let count = self
+            .pseudonyms
+            .iter()
+            .filter(|p| self::prefix(&p.label) == prefix)
+            .count();
+        let placeholder = format!("{}_{}", prefix, count + 1);
+        self.pseudonyms.push(Pseudonym {
+            placeholder: placeholder.clone(),
+            label: label.to_string(),
+            value: value.to_string(),
+        });
+        placeholder
+    }
+
+    // Replaces the entities of `result` in `text` with their placeholders.
+    // Overlapping entities are replaced as one region, as in `redact`.
+    pub fn pseudonymize(&mut self, text: &str, result: &InferenceResultSingle) -> Redaction {
+        replace_entities(text, result, |span, label| self.placeholder(label, span))
+    }
+
+    // Restores the values of the placeholders found in `text`. Placeholders
+    // are matched regardless of case and of the separators between their
+    // words and number, so `Person 1` and `person-1` give back the value of
+    // `PERSON_1`.
+    pub fn rehydrate(&self, text: &str) -> String {
+        if self.pseudonyms.is_empty() {
+            return text.to_string();
+        }
+
+        let mut values: HashMap<(String, &str), &str> = HashMap::new();
+        let mut prefixes: Vec<String> = Vec::new();
+        for pseudonym in &self.pseudonyms {
+            let Some((prefix, number)) = pseudonym.placeholder.rsplit_once('_') else {
+                continue;
+            };
+            values.insert((compact(prefix), number), &pseudonym.value);
+            let pattern = prefix
+                .split('_')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(r"[\s_-]?");
+            if !prefixes.contains(&pattern) {
+                prefixes.push(pattern);
+            }
+        }
+        // Longest first, so that `EMAIL_ADDRESS` is not read as `EMAIL`.
+        prefixes.sort_by_key(|pattern| std::cmp::Reverse(pattern.len()));
+
+        let pattern = format!(r"(?i)\b({})[\s_-]?(\d+)\b", prefixes.join("|"));
+        let Ok(placeholders) = Regex::new(&pattern) else {
+            return text.to_string();
+        };
+        placeholders
+            .replace_all(text, |captures: &regex::Captures| {
+                values
+                    .get(&(compact(&captures[1]), &captures[2]))
+                    .map(|value| value.to_string())
+                    .unwrap_or_else(|| captures[0].to_string())
+            })
+            .into_owned()
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content =
+            std::fs::read_to_string(path).map_err(|e| GlinerError::file("reading", path, e))?;
+        serde_json::from_str(&content).map_err(|e| GlinerError::file("parsing", path, e))
+    }
+
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| GlinerError::file("writing", path, e))?;
+        std::fs::write(path, content).map_err(|e| GlinerError::file("writing", path, e))
+    }
+}
@@ -0,0 +1,276 @@
+use crate::error::{GlinerError, Result};
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use regex::Regex;
+
+// Deterministic detector of one label, run on texts next to the model. Returns
+// the `(start, end, score)` byte spans it found.
+pub trait Recognizer: Send + Sync {
+    fn label(&self) -> &str;
+    fn recognize(&self, text: &str) -> Vec<(usize, usize, f32)>;
+}
+
+// Matches of a regular expression, optionally filtered by a validator such as
+// a checksum.
+pub struct PatternRecognizer {
+    label: String,
+    pattern: Regex,
+    validator: Option<fn(&str) -> bool>,
+    score: f32,
+}
+
+impl PatternRecognizer {
+    pub fn new(label: &str, pattern: &str) -> Result<Self> {
+        Ok(PatternRecognizer {
+            label: label.to_string(),
+            pattern: Regex::new(pattern).map_err(|e| {
+                GlinerError::InitializationError(format!(
+                    "Invalid pattern {} for {}: {}",
+                    pattern, label, e
+                ))
+            })?,
+            validator: None,
+            score: 1.0,
+        })
+    }
+
+    pub fn with_validator(mut self, validator: fn(&str) -> bool) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    pub fn with_score(mut self, score: f32) -> Self {
+        self.score = score;
+        self
+    }
+
+    pub fn email(label: &str) -> Self {
+        Self::new(label, r"(?i)\b[A-Z0-9._%+-]+@[A-Z0-9.-]+\.[A-Z]{2,}\b").unwrap()
+    }
+
+    // Card numbers of 13 to 19 digits, optionally grouped with spaces or
+    // hyphens, passing the Luhn checksum.
+    pub fn credit_card(label: &str) -> Self {
+        Self::new(label, r"\b\d(?:[ -]?\d){12,18}\b")
+            .unwrap()
+            .with_validator(luhn_valid)
+    }
+
+    // IBANs written in one block or in groups of four, passing the mod 97
+    // checksum.
+    pub fn iban(label: &str) -> Self {
+        Self::new(
+            label,
+            r"\b[A-Z]{2}\d{2}(?:[A-Z0-9]{11,30}|(?: [A-Z0-9]{4}){2,7}(?: [A-Z0-9]{1,4})?)\b",
+        )
+        .unwrap()
+        .with_validator(iban_valid)
+    }
+
+    // Phone numbers of 7 to 15 digits with an optional country code and area
+    // code in parentheses. Dates such as 2024-01-15 are not phone numbers.
+    pub fn phone(label: &str) -> Self {
+        Self::new(
+            label,
+            r"(?:\+\d{1,3}[\s.-]?(?:\(\d{1,4}\)[\s.-]?)?|\(\d{1,4}\)[\s.-]?|\b)\d{2,4}(?:[\s.-]?\d{2,4}){1,4}\b",
+        )
+        .unwrap()
+        .with_validator(|number| {
+            let digits = number.chars().filter(char::is_ascii_digit).count();
+            (7..=15).contains(&digits) && !is_date(number)
+        })
+    }
+
+    // Length of the longest prefix of `matched`, cut at a space, that is a
+    // match on its own and passes the validator. Grouped identifiers may run
+    // on into a following word that looks like one more group.
+    fn valid_length(&self, matched: &str) -> Option<usize> {
+        let Some(valid) = self.validator else {
+            return Some(matched.len());
+        };
+        std::iter::once(matched.len())
+            .chain(matched.rmatch_indices(' ').map(|(space, _)| space))
+            .find(|&end| {
+                let prefix = &matched[..end];
+                let whole = end == matched.len()
+                    || self
+                        .pattern
+                        .find(prefix)
+                        .is_some_and(|m| m.start() == 0 && m.end() == end);
+                whole && valid(prefix)
+            })
+    }
+}
+
+impl Recognizer for PatternRecognizer {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn recognize(&self, text: &str) -> Vec<(usize, usize, f32)> {
+        self.pattern
+            .find_iter(text)
+            .filter_map(|m| {
+                self.valid_length(m.as_str())
+                    .map(|length| (m.start(), m.start() + length, self.score))
+            })
+            .collect()
+    }
+}
+
+// Occurrences of known terms, e.g. customer names, matched as whole words.
+pub struct GazetteerRecognizer {
+    label: String,
+    automaton: AhoCorasick,
+    score: f32,
+}
+
+impl GazetteerRecognizer {
+    pub fn new<S: AsRef<str>>(label: &str, terms: &[S], case_insensitive: bool) -> Result<Self> {
+        let automaton = AhoCorasickBuilder::new()
+            .ascii_case_insensitive(case_insensitive)
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(terms.iter().map(|term| term.as_ref()))
+            .map_err(|e| {
+                GlinerError::InitializationError(format!(
+                    "Error building gazetteer for {}: {}",
+                    label, e
+                ))
+            })?;
+        Ok(GazetteerRecognizer {
+            label: label.to_string(),
+            automaton,
+            score: 1.0,
+        })
+    }
+
+    pub fn with_score(mut self, score: f32) -> Self {
+        self.score = score;
+        self
+    }
+}
+
+impl Recognizer for GazetteerRecognizer {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn recognize(&self, text: &str) -> Vec<(usize, usize, f32)> {
+        let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+        self.automaton
+            .find_iter(text)
+            .filter(|m| {
+                !is_word_char(text[..m.start()].chars().next_back())
+                    && !is_word_char(text[m.end()..].chars().next())
+            })
+            .map(|m| (m.start(), m.end(), self.score))
+            .collect()
+    }
+}
+
+// Recognizers whose matches are merged with the model candidates before
+// overlap resolution.
+#[derive(Default)]
+pub struct RecognizerRegistry {
+    recognizers: Vec<Box<dyn Recognizer>>,
+}
+
+impl RecognizerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<R: Recognizer + 'static>(mut self, recognizer: R) -> Self {
+        self.recognizers.push(Box::new(recognizer));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recognizers.is_empty()
+    }
+
+    pub fn recognizers(&self) -> &[Box<dyn Recognizer>] {
+        &self.recognizers
+    }
+
+    // `(start, end, label, score)` matches of every recognizer in `text`.
+    pub fn recognize<'a>(&'a self, text: &str) -> Vec<(usize, usize, &'a str, f32)> {
+        self.recognizers
+            .iter()
+            .flat_map(|recognizer| {
+                recognizer
+                    .recognize(text)
+                    .into_iter()
+                    .map(|(start, end, score)| (start, end, recognizer.label(), score))
+            })
+            .collect()
+    }
+}
+
+// Luhn checksum of the digits of `number`, used by card numbers.
+pub fn luhn_valid(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 2 {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(position, &digit)| match (position % 2, digit * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => digit,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+// Whether `text` reads as a date: three numbers split by one of `-`, `/` or
+// `.`, with the year first or last and a plausible month and day.
+pub fn is_date(text: &str) -> bool {
+    let Some(separator) = text.chars().find(|c| matches!(c, '-' | '/' | '.')) else {
+        return false;
+    };
+    let parts: Vec<&str> = text.split(separator).collect();
+    if parts.len() != 3
+        || !parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    {
+        return false;
+    }
+    let number = |part: &str| part.parse::<u32>().unwrap_or(0);
+    let (first, second, third) = (number(parts[0]), number(parts[1]), number(parts[2]));
+    match (parts[0].len(), parts[1].len(), parts[2].len()) {
+        (4, 1..=2, 1..=2) => (1..=12).contains(&second) && (1..=31).contains(&third),
+        (1..=2, 1..=2, 2 | 4) => {
+            (1..=31).contains(&first) && (1..=31).contains(&second) && (first <= 12 || second <= 12)
+        }
+        _ => false,
+    }
+}
+
+// ISO 13616 check digits of an IBAN, ignoring spaces.
+pub fn iban_valid(iban: &str) -> bool {
+    let iban: Vec<char> = iban
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if !(15..=34).contains(&iban.len()) || !iban.iter().all(char::is_ascii_alphanumeric) {
+        return false;
+    }
+
+    // Moves the country code and check digits to the end and reads letters as
+    // 10 to 35, computing the remainder digit by digit.
+    let mut remainder: u32 = 0;
+    for c in iban[4..].iter().chain(&iban[..4]) {
+        let value = c.to_digit(36).unwrap();
+        remainder = if value < 10 {
+            (remainder * 10 + value) % 97
+        } else {
+            (remainder * 100 + value) % 97
+        };
+    }
+    remainder == 1
+}
@@ -0,0 +1,236 @@
+use crate::types::InferenceResultSingle;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// How the text of an entity is replaced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "operator", rename_all = "snake_case")]
+pub enum RedactionOperator {
+    // Replaces every non-whitespace character with `mask`.
+    Mask { mask: char },
+    // Replaces the entity with `value`, or with `<LABEL>` when unset.
+    Replace { value: Option<String> },
+    // First `length` hex digits of the HMAC-SHA256 of the entity under `key`,
+    // so that equal values stay linkable without being readable.
+    Hash { key: String, length: usize },
+    // Random value of the same shape: digits, lowercase and uppercase letters
+    // are replaced by ones of the same class, anything else is kept. Seeded by
+    // the HMAC of the entity under `key`, so equal values get equal fakes.
+    Fake { key: String },
+    // Removes the entity.
+    Drop,
+}
+
+impl Default for RedactionOperator {
+    fn default() -> Self {
+        RedactionOperator::Replace { value: None }
+    }
+}
+
+impl RedactionOperator {
+    pub fn mask() -> Self {
+        RedactionOperator::Mask { mask: '*' }
+    }
+
+    fn apply(&self, span: &str, label: &str) -> String {
+        match self {
+            RedactionOperator::Mask { mask } => span
+                .chars()
+                .map(|c| if c.is_whitespace() { c } else { *mask })
+                .collect(),
+            RedactionOperator::Replace { value } => value
+                .clone()
+                .unwrap_or_else(|| format!("<{}>", label.to_uppercase())),
+            RedactionOperator::Hash { key, length } => {
+                let digest = keyed_digest(key, 0, span);
+                let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+                hex.chars().take(*length).collect()
+            }
+            RedactionOperator::Fake { key } => fake(key, span),
+            RedactionOperator::Drop => String::new(),
+        }
+    }
+}
+
+// Operators applied to each label. Labels without an entry use `default`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionPolicy {
+    pub default: RedactionOperator,
+    pub labels: HashMap<String, RedactionOperator>,
+}
+
+impl RedactionPolicy {
+    pub fn new(default: RedactionOperator) -> Self {
+        RedactionPolicy {
+            default,
+            labels: HashMap::new(),
+        }
+    }
+
+    pub fn with_label(mut self, label: &str, operator: RedactionOperator) -> Self {
+        self.labels.insert(label.to_string(), operator);
+        self
+    }
+
+    pub fn operator_for(&self, label: &str) -> &RedactionOperator {
+        self.labels.get(label).unwrap_or(&self.default)
+    }
+}
+
+// A replaced region, as byte ranges of the redacted and of the original text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactedSpan {
+    pub start: usize,
+    pub end: usize,
+    pub original_start: usize,
+    pub original_end: usize,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Redaction {
+    pub text: String,
+    // Replaced regions in order, which also map redacted offsets back to the
+    // original text.
+    pub spans: Vec<RedactedSpan>,
+}
+
+impl Redaction {
+    // Offset in the original text of byte `position` of the redacted text.
+    // Positions inside a replacement map to the start of what it replaced.
+    pub fn original_offset(&self, position: usize) -> usize {
+        self.map_back(position, false)
+    }
+
+    // Original byte range of `start..end` in the redacted text. Ranges
+    // touching a replacement are widened to everything it replaced.
+    pub fn original_range(&self, start: usize, end: usize) -> (usize, usize) {
+        (self.map_back(start, false), self.map_back(end, true))
+    }
+
+    fn map_back(&self, position: usize, is_end: bool) -> usize {
+        // A dropped entity leaves an empty region, which still counts as
+        // touching a position equal to it.
+        let touches = |span: &RedactedSpan| span.start == position && span.end == position;
+        if is_end {
+            let before = self
+                .spans
+                .partition_point(|span| span.start < position || touches(span));
+            return match before.checked_sub(1).map(|id| &self.spans[id]) {
+                None => position,
+                Some(span) if position <= span.end => span.original_end,
+                Some(span) => position - span.end + span.original_end,
+            };
+        }
+
+        let before = self.spans.partition_point(|span| {
+            span.end < position || (span.end == position && !touches(span))
+        });
+        match self.spans.get(before) {
+            Some(span) if span.start <= position => span.original_start,
+            _ => match before.checked_sub(1).map(|id| &self.spans[id]) {
+                None => position,
+                Some(span) => position - span.end + span.original_end,
+            },
+        }
+    }
+}
+
+// Replaces the entities of `result` in `text` according to `policy`.
+// Overlapping or nested entities are redacted as one region, using the
+// operator of the longest of them. Entities whose offsets do not fall on
+// character boundaries of `text` are ignored.
+pub fn redact(text: &str, result: &InferenceResultSingle, policy: &RedactionPolicy) -> Redaction {
+    replace_entities(text, result, |span, label| {
+        policy.operator_for(label).apply(span, label)
+    })
+}
+
+// Replaces each region of overlapping entities of `result` with what `replace`
+// returns for its text and the label of its longest entity.
+pub(crate) fn replace_entities<F>(
+    text: &str,
+    result: &InferenceResultSingle,
+    mut replace: F,
+) -> Redaction
+where
+    F: FnMut(&str, &str) -> String,
+{
+    let mut entities: Vec<(usize, usize, &str)> = result
+        .entities
+        .iter()
+        .filter(|entity| entity.start < entity.end && text.get(entity.start..entity.end).is_some())
+        .map(|entity| (entity.start, entity.end, entity.label.as_str()))
+        .collect();
+    entities.sort_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+
+    // `(start, end, label, length)` of the regions to replace, `label` and
+    // `length` being those of the longest entity in the region.
+    let mut regions: Vec<(usize, usize, &str, usize)> = Vec::new();
+    for (start, end, label) in entities {
+        match regions.last_mut() {
+            Some(region) if start < region.1 => {
+                if end - start > region.3 {
+                    region.2 = label;
+                    region.3 = end - start;
+                }
+                region.1 = region.1.max(end);
+            }
+            _ => regions.push((start, end, label, end - start)),
+        }
+    }
+
+    let mut redacted = String::with_capacity(text.len());
+    let mut spans = Vec::with_capacity(regions.len());
+    let mut copied = 0;
+    for (start, end, label, _) in regions {
+        redacted.push_str(&text[copied..start]);
+        let replacement = replace(&text[start..end], label);
+        spans.push(RedactedSpan {
+            start: redacted.len(),
+            end: redacted.len() + replacement.len(),
+            original_start: start,
+            original_end: end,
+            label: label.to_string(),
+        });
+        redacted.push_str(&replacement);
+        copied = end;
+    }
+    redacted.push_str(&text[copied..]);
+
+    Redaction {
+        text: redacted,
+        spans,
+    }
+}
+
+fn keyed_digest(key: &str, block: u32, span: &str) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(&block.to_le_bytes());
+    mac.update(span.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+fn fake(key: &str, span: &str) -> String {
+    let mut stream = (0u32..).flat_map(|block| keyed_digest(key, block, span));
+    span.chars()
+        .map(|c| {
+            let class: &[u8] = if c.is_ascii_digit() {
+                b"0123456789"
+            } else if c.is_lowercase() {
+                b"abcdefghijklmnopqrstuvwxyz"
+            } else if c.is_uppercase() {
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+            } else {
+                return c;
+            };
+            let byte = stream.next().unwrap_or_default() as usize;
+            class[byte % class.len()] as char
+        })
+        .collect()
+}
@@ -1,14 +1,47 @@
 use serde::{Deserialize, Serialize};
 
 pub type RawInferenceResult = Vec<Vec<(String, usize, usize, String, f32)>>;
+// Ranked `(label, score)` pairs of every span of a `RawInferenceResult`, empty
+// unless label scores were requested.
+pub type RawLabelScores = Vec<Vec<Vec<(String, f32)>>>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelScore {
+    pub label: String,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EntityResult {
     pub span_text: String,
     pub start: usize,
     pub end: usize,
     pub label: String,
     pub score: f32,
+    // Index of the sentence the entity starts in, when the text was split into
+    // sentences before inference.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentence_index: Option<usize>,
+    // Candidate labels of the span ranked by score, filled when
+    // `InferenceOptions::top_k` is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub label_scores: Vec<LabelScore>,
+    // Index of the first word of the entity and of the word after it, for
+    // alignment with token-level tooling.
+    #[serde(default)]
+    pub word_start: usize,
+    #[serde(default)]
+    pub word_end: usize,
+    // Offsets in code points or UTF-16 code units, set according to
+    // `InferenceOptions::offset_unit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub char_start: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub char_end: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utf16_start: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utf16_end: Option<usize>,
 }
 
 impl std::fmt::Display for EntityResult {
@@ -25,3 +58,74 @@ pub struct InferenceResultSingle {
     pub entities: Vec<EntityResult>,
 }
 pub type InferenceResultMultiple = Vec<InferenceResultSingle>;
+
+// Entities of one text arranged by containment, e.g. `organization`
+// "Bank of America" with `location` "America" as a child. Nodes are stored in
+// order of start offset, outer spans first, and link to each other by index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityTree {
+    pub nodes: Vec<EntityNode>,
+    // Indices of the nodes not contained in any other entity.
+    pub roots: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityNode {
+    #[serde(flatten)]
+    pub entity: EntityResult,
+    // Number of entities containing this one.
+    pub depth: usize,
+    // Index of the smallest entity containing this one.
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+impl EntityTree {
+    // Builds the tree from possibly nested entities. Entities sharing the same
+    // offsets (multi-label results) are siblings rather than parent and child.
+    pub fn from_entities(entities: &[EntityResult]) -> Self {
+        let mut sorted: Vec<&EntityResult> = entities.iter().collect();
+        sorted.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+        let mut tree = EntityTree {
+            nodes: Vec::with_capacity(sorted.len()),
+            roots: Vec::new(),
+        };
+        // Chain of entities containing the current position.
+        let mut open: Vec<usize> = Vec::new();
+        for entity in sorted {
+            while let Some(&top) = open.last() {
+                let outer = &tree.nodes[top].entity;
+                let contains = outer.start <= entity.start
+                    && entity.end <= outer.end
+                    && (outer.start, outer.end) != (entity.start, entity.end);
+                if contains {
+                    break;
+                }
+                open.pop();
+            }
+
+            let id = tree.nodes.len();
+            let parent = open.last().copied();
+            match parent {
+                Some(parent) => tree.nodes[parent].children.push(id),
+                None => tree.roots.push(id),
+            }
+            tree.nodes.push(EntityNode {
+                entity: entity.clone(),
+                depth: parent.map_or(0, |parent| tree.nodes[parent].depth + 1),
+                parent,
+                children: Vec::new(),
+            });
+            open.push(id);
+        }
+
+        tree
+    }
+}
+
+impl InferenceResultSingle {
+    pub fn to_tree(&self) -> EntityTree {
+        EntityTree::from_entities(&self.entities)
+    }
+}
@@ -0,0 +1,43 @@
+use glinerrust::batching::{plan_batches, BatchingConfig};
+
+fn config(batch_size: usize, max_tokens: Option<usize>) -> BatchingConfig {
+    BatchingConfig {
+        batch_size,
+        max_tokens,
+    }
+}
+
+#[test]
+fn texts_are_batched_by_increasing_length() {
+    let token_counts = [7, 2, 9, 2, 5];
+    assert_eq!(
+        plan_batches(&token_counts, &config(2, None)),
+        vec![vec![1, 3], vec![4, 0], vec![2]]
+    );
+    assert_eq!(
+        plan_batches(&token_counts, &config(8, None)),
+        vec![vec![1, 3, 4, 0, 2]]
+    );
+    // A batch size of zero still makes progress.
+    assert_eq!(plan_batches(&[3, 1], &config(0, None)).len(), 2);
+}
+
+#[test]
+fn max_tokens_caps_the_padded_batch() {
+    // 2 * 5 = 10 fits, 3 * 7 = 21 does not.
+    let token_counts = [5, 5, 7, 30];
+    assert_eq!(
+        plan_batches(&token_counts, &config(8, Some(16))),
+        vec![vec![0, 1], vec![2], vec![3]]
+    );
+}
+
+#[test]
+fn texts_without_tokens_are_left_out() {
+    assert_eq!(
+        plan_batches(&[0, 4, 0, 1], &config(8, None)),
+        vec![vec![3, 1]]
+    );
+    assert!(plan_batches(&[0, 0], &config(8, None)).is_empty());
+    assert!(plan_batches(&[], &config(8, None)).is_empty());
+}
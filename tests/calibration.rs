@@ -0,0 +1,127 @@
+use glinerrust::calibration::{
+    calibration_samples, Calibration, CalibrationMethod, CalibrationSample, LabelCalibration,
+};
+use glinerrust::gliner::InitConfig;
+use glinerrust::{EntityResult, Gliner, GlinerError, InferenceResultSingle};
+
+fn sample(label: &str, score: f32, correct: bool) -> CalibrationSample {
+    CalibrationSample {
+        label: label.to_string(),
+        score,
+        correct,
+    }
+}
+
+// An overconfident label: spans scored 0.95 are right only 70% of the time
+// and spans scored 0.6 only 40% of the time.
+fn overconfident(label: &str) -> Vec<CalibrationSample> {
+    let mut samples = Vec::new();
+    for id in 0..100 {
+        samples.push(sample(label, 0.95, id < 70));
+        samples.push(sample(label, 0.6, id < 40));
+    }
+    samples
+}
+
+#[test]
+fn temperature_softens_an_overconfident_label() {
+    let mut samples = overconfident("person");
+    samples.push(sample("date", 0.9, true));
+
+    let calibration = Calibration::fit(&samples, CalibrationMethod::Temperature);
+
+    let person = calibration.label("person");
+    assert!(person.scale < 1.0);
+    assert_eq!(person.bias, 0.0);
+    // Labels without both correct and incorrect samples are left untouched.
+    assert!(!calibration.labels.contains_key("date"));
+    assert_eq!(calibration.label("date").scale, 1.0);
+}
+
+#[test]
+fn platt_matches_the_observed_accuracy() {
+    let calibration = Calibration::fit(&overconfident("person"), CalibrationMethod::Platt);
+
+    let person = calibration.label("person");
+    let probability = |score: f32| {
+        let logit = (score / (1.0 - score)).ln();
+        1.0 / (1.0 + (-person.logit(logit)).exp())
+    };
+    assert!((probability(0.95) - 0.7).abs() < 0.03);
+    assert!((probability(0.6) - 0.4).abs() < 0.03);
+}
+
+#[test]
+fn calibration_round_trips_through_json() {
+    let calibration = Calibration::fit(&overconfident("person"), CalibrationMethod::Platt);
+    let path = std::env::temp_dir().join("glinerrust-calibration-test.json");
+
+    calibration.to_file(&path).unwrap();
+    let loaded = Calibration::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.method, CalibrationMethod::Platt);
+    assert_eq!(loaded.label("person"), calibration.label("person"));
+}
+
+#[test]
+fn calibrations_reversing_scores_are_rejected() {
+    let path = std::env::temp_dir().join("glinerrust-invalid-calibration-test.json");
+    for scale in ["0.0", "-2.0", "0.0001"] {
+        let json = format!(r#"{{"labels": {{"person": {{"scale": {}}}}}}}"#, scale);
+        std::fs::write(&path, json).unwrap();
+        assert!(matches!(
+            Calibration::from_file(&path),
+            Err(GlinerError::InitializationError(_))
+        ));
+    }
+    std::fs::write(
+        &path,
+        r#"{"labels": {"person": {"scale": 0.5, "bias": -1.0}}}"#,
+    )
+    .unwrap();
+    let loaded = Calibration::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.label("person").scale, 0.5);
+}
+
+#[test]
+fn calibrations_set_in_code_are_validated() {
+    let mut gliner = Gliner::new(InitConfig::default());
+    let mut calibration = Calibration::default();
+    calibration.labels.insert(
+        "person".to_string(),
+        LabelCalibration {
+            scale: 0.0,
+            bias: 0.0,
+        },
+    );
+    assert!(matches!(
+        gliner.set_calibration(calibration),
+        Err(GlinerError::InitializationError(_))
+    ));
+    assert!(gliner.set_calibration(Calibration::default()).is_ok());
+}
+
+#[test]
+fn samples_are_matched_against_gold_entities() {
+    let entity = |start, end, label: &str, score| EntityResult {
+        span_text: String::new(),
+        start,
+        end,
+        label: label.to_string(),
+        score,
+        ..Default::default()
+    };
+    let predictions = vec![InferenceResultSingle {
+        entities: vec![entity(0, 5, "person", 0.9), entity(10, 15, "date", 0.4)],
+    }];
+    let gold = vec![vec![(0, 5, "person".to_string())]];
+
+    let samples = calibration_samples(&predictions, &gold);
+
+    assert_eq!(
+        samples,
+        vec![sample("person", 0.9, true), sample("date", 0.4, false)]
+    );
+}
@@ -0,0 +1,61 @@
+use glinerrust::chunking::{build_windows, plan_windows, Window};
+
+// Offsets of `count` three-letter words separated by single spaces.
+fn words(count: usize) -> (Vec<usize>, Vec<usize>) {
+    (0..count).map(|word| (4 * word, 4 * word + 3)).unzip()
+}
+
+#[test]
+fn windows_cover_every_word_within_budget() {
+    let windows = plan_windows(&[1, 2, 1, 1, 3, 1], 4, 1);
+    assert_eq!(windows, vec![0..3, 2..4, 3..5, 4..6]);
+
+    // A word over the budget gets a window of its own.
+    assert_eq!(plan_windows(&[1, 9, 1], 4, 1), vec![0..1, 1..2, 2..3]);
+    assert!(plan_windows(&[], 4, 1).is_empty());
+}
+
+#[test]
+fn overlap_is_capped_at_half_a_window() {
+    let windows = plan_windows(&[1; 100], 10, 32);
+    assert_eq!(windows.len(), 19);
+    assert_eq!(windows[1], 5..15);
+    assert_eq!(windows.last().unwrap().end, 100);
+}
+
+#[test]
+fn windows_know_their_cut_neighbours() {
+    let (starts, ends) = words(6);
+    let windows = build_windows(3, &starts, &ends, &[1; 6], None, 4, 2);
+    let ranges: Vec<_> = windows.iter().map(|window| window.words.clone()).collect();
+    assert_eq!(ranges, vec![0..4, 2..6]);
+
+    let first = &windows[0];
+    assert_eq!(
+        (first.text_id, first.byte_start, first.byte_end),
+        (3, 0, 15)
+    );
+    assert!(!first.cut_start && first.cut_end);
+    assert_eq!((first.previous, first.next), (None, Some((8, 23))));
+    assert_eq!(windows[1].previous, Some((0, 15)));
+}
+
+#[test]
+fn spans_on_cut_edges_are_left_to_the_window_seeing_them_whole() {
+    let (starts, ends) = words(6);
+    let windows: Vec<Window> = build_windows(0, &starts, &ends, &[1; 6], None, 4, 2);
+
+    // Word 3 ends the first window but lies inside the second one.
+    assert!(!windows[0].keeps(12, 15));
+    assert!(windows[1].keeps(12, 15));
+    // Word 2 starts the second window but lies inside the first one.
+    assert!(windows[0].keeps(8, 11));
+    assert!(!windows[1].keeps(8, 11));
+
+    // With one shared word, neither window sees it away from an edge, so
+    // both keep it.
+    let windows = build_windows(0, &starts, &ends, &[1; 6], None, 4, 1);
+    assert_eq!(windows[1].words, 3..6);
+    assert!(windows[0].keeps(12, 15));
+    assert!(windows[1].keeps(12, 15));
+}
@@ -0,0 +1,49 @@
+// Fixtures shared by the integration tests. Not every test file uses all of
+// them.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use tokenizers::models::wordlevel::WordLevel;
+use tokenizers::pre_tokenizers::whitespace::Whitespace;
+use tokenizers::processors::bert::BertProcessing;
+use tokenizers::{AddedToken, Tokenizer};
+
+// Word level tokenizer over `vocab`, split on whitespace, without special
+// tokens.
+pub fn word_level(vocab: &[&str]) -> Tokenizer {
+    let vocab: HashMap<String, u32> = vocab
+        .iter()
+        .enumerate()
+        .map(|(id, token)| (token.to_string(), id as u32))
+        .collect();
+    let model = WordLevel::builder()
+        .vocab(vocab)
+        .unk_token("[UNK]".to_string())
+        .build()
+        .unwrap();
+    let mut tokenizer = Tokenizer::new(model);
+    tokenizer.with_pre_tokenizer(Some(Whitespace {}));
+    tokenizer
+}
+
+// Adds the GLiNER marker tokens to `tokenizer`.
+pub fn with_markers(mut tokenizer: Tokenizer) -> Tokenizer {
+    tokenizer.add_special_tokens(&[
+        AddedToken::from("<<ENT>>", true),
+        AddedToken::from("<<SEP>>", true),
+    ]);
+    tokenizer
+}
+
+// BERT-like tokenizer over a handful of words, with the GLiNER marker tokens.
+pub fn tokenizer() -> Tokenizer {
+    let mut tokenizer = word_level(&[
+        "[UNK]", "[CLS]", "[SEP]", "alice", "met", "bob", "person", "city",
+    ]);
+    tokenizer.with_post_processor(Some(BertProcessing::new(
+        ("[SEP]".to_string(), 2),
+        ("[CLS]".to_string(), 1),
+    )));
+    with_markers(tokenizer)
+}
@@ -0,0 +1,33 @@
+use glinerrust::config::ModelConfig;
+use glinerrust::decoder::SpanMode;
+
+fn parse(json: &str) -> ModelConfig {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn missing_fields_keep_their_defaults() {
+    // Keys the crate does not use are ignored.
+    let config = parse(r#"{"max_width": 8, "model_name": "microsoft/deberta-v3-base"}"#);
+    assert_eq!(config.max_width, 8);
+    assert_eq!(config.max_len, 512);
+    assert_eq!(config.ent_token, "<<ENT>>");
+    assert_eq!(config.sep_token, "<<SEP>>");
+    assert_eq!(config.words_splitter_type, "whitespace");
+    assert_eq!(config.words_splitter_pattern, None);
+    assert_eq!(config.span_mode, None);
+}
+
+#[test]
+fn span_mode_is_read_from_gliner_names() {
+    assert_eq!(
+        parse(r#"{"span_mode": "markerV0"}"#).span_mode,
+        Some(SpanMode::Span)
+    );
+    assert_eq!(
+        parse(r#"{"span_mode": "token_level"}"#).span_mode,
+        Some(SpanMode::TokenLevel)
+    );
+    // An explicit null leaves the mode to detection, like a missing key.
+    assert_eq!(parse(r#"{"span_mode": null}"#).span_mode, None);
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use glinerrust::calibration::LabelCalibration;
+use glinerrust::decoder::{
+    pool_labels, BaseDecoder, Decoder, SelectionStrategy, SpanCandidate, SpanDecoder,
+};
+
+fn decoder() -> SpanDecoder {
+    SpanDecoder::new()
+}
+
+fn span(start: usize, end: usize, class: usize, score: f32) -> SpanCandidate {
+    SpanCandidate::new(start, end, class, score)
+}
+
+#[test]
+fn flat_keeps_best_of_overlapping_spans() {
+    let mut spans = vec![
+        span(0, 10, 0, 0.6),
+        span(5, 15, 1, 0.9),
+        span(20, 25, 0, 0.7),
+    ];
+
+    let selected = decoder().greedy_search(&mut spans, true, false);
+
+    assert_eq!(selected, vec![span(5, 15, 1, 0.9), span(20, 25, 0, 0.7)]);
+}
+
+#[test]
+fn flat_rejects_nested_spans() {
+    let mut spans = vec![span(0, 15, 0, 0.9), span(8, 15, 1, 0.8)];
+
+    let selected = decoder().greedy_search(&mut spans, true, false);
+
+    assert_eq!(selected, vec![span(0, 15, 0, 0.9)]);
+}
+
+#[test]
+fn nested_keeps_contained_spans_but_not_partial_overlaps() {
+    let mut spans = vec![
+        span(0, 15, 0, 0.9),
+        span(8, 15, 1, 0.8),
+        span(10, 20, 2, 0.7),
+    ];
+
+    let selected = decoder().greedy_search(&mut spans, false, false);
+
+    assert_eq!(selected, vec![span(0, 15, 0, 0.9), span(8, 15, 1, 0.8)]);
+}
+
+#[test]
+fn single_label_keeps_one_label_per_span() {
+    for flat_ner in [true, false] {
+        let mut spans = vec![span(0, 5, 0, 0.7), span(0, 5, 1, 0.8)];
+
+        let selected = decoder().greedy_search(&mut spans, flat_ner, false);
+
+        assert_eq!(selected, vec![span(0, 5, 1, 0.8)]);
+    }
+}
+
+#[test]
+fn multi_label_keeps_every_label_of_a_span() {
+    for flat_ner in [true, false] {
+        let mut spans = vec![span(0, 5, 0, 0.7), span(0, 5, 1, 0.8)];
+
+        let mut selected = decoder().greedy_search(&mut spans, flat_ner, true);
+        selected.sort_by_key(|span| span.class_id);
+
+        assert_eq!(selected, vec![span(0, 5, 0, 0.7), span(0, 5, 1, 0.8)]);
+    }
+}
+
+#[test]
+fn multi_label_flat_still_rejects_overlapping_spans() {
+    let mut spans = vec![span(0, 5, 0, 0.9), span(0, 5, 1, 0.8), span(3, 9, 1, 0.85)];
+
+    let selected = decoder().greedy_search(&mut spans, true, true);
+
+    assert_eq!(selected.len(), 2);
+    assert!(selected.iter().all(|s| s.offsets() == (0, 5)));
+}
+
+#[test]
+fn multi_label_nested_keeps_labels_of_nested_spans() {
+    let mut spans = vec![
+        span(0, 15, 0, 0.9),
+        span(8, 15, 1, 0.8),
+        span(8, 15, 2, 0.75),
+        span(10, 20, 2, 0.7),
+    ];
+
+    let selected = decoder().greedy_search(&mut spans, false, true);
+
+    assert_eq!(selected.len(), 3);
+    assert!(!selected.contains(&span(10, 20, 2, 0.7)));
+}
+
+#[test]
+fn decode_returns_a_span_once_per_label_with_multi_label() {
+    let texts = vec!["Paris".to_string()];
+    let id_to_class = HashMap::from([(0, "location".to_string()), (1, "organization".to_string())]);
+    // One word, max_width 1 and two labels, both clearly over threshold.
+    let logits = [2.0, 1.0];
+
+    let decode = |multi_label| {
+        Decoder::decode(
+            &decoder(),
+            1,
+            1,
+            1,
+            2,
+            &texts,
+            &[0],
+            &[vec![0]],
+            &[vec![5]],
+            &id_to_class,
+            &logits,
+            true,
+            0.5,
+            multi_label,
+        )
+    };
+
+    let single = decode(false);
+    assert_eq!(single[0].len(), 1);
+    assert_eq!(single[0][0].3, "location");
+
+    let mut labels: Vec<String> = decode(true)[0]
+        .iter()
+        .map(|(text, _, _, label, _)| {
+            assert_eq!(text, "Paris");
+            label.clone()
+        })
+        .collect();
+    labels.sort();
+    assert_eq!(labels, vec!["location", "organization"]);
+}
+
+#[test]
+fn decode_candidates_applies_the_threshold_of_each_label() {
+    // sigmoid(0.0) = 0.5: only the label with the lower threshold keeps it.
+    let logits = [0.0, 0.0];
+
+    let spans = Decoder::decode_candidates(
+        &decoder(),
+        1,
+        1,
+        1,
+        2,
+        &[vec![0]],
+        &[vec![5]],
+        &logits,
+        &[0.6, 0.4],
+        &[],
+    );
+
+    assert_eq!(spans, vec![vec![span(0, 5, 1, 0.5)]]);
+}
+
+#[test]
+fn decode_candidates_thresholds_calibrated_scores() {
+    let logits = [0.0, 0.0];
+    let calibration = [
+        LabelCalibration {
+            scale: 1.0,
+            bias: 1.0,
+        },
+        LabelCalibration {
+            scale: 0.5,
+            bias: -1.0,
+        },
+    ];
+
+    let spans = Decoder::decode_candidates(
+        &decoder(),
+        1,
+        1,
+        1,
+        2,
+        &[vec![0]],
+        &[vec![5]],
+        &logits,
+        &[0.6, 0.2],
+        &calibration,
+    );
+
+    // sigmoid(1.0) and sigmoid(-1.0).
+    assert_eq!(spans[0].len(), 2);
+    assert!((spans[0][0].score - 0.7311).abs() < 1e-4);
+    assert!((spans[0][1].score - 0.2689).abs() < 1e-4);
+}
+
+#[test]
+fn span_scores_returns_every_label_of_a_span() {
+    // Two words, max_width 2 and two labels; the span of both words starts at
+    // the first word with a width offset of 1.
+    let logits = [0.0, 0.0, 2.0, -2.0, 0.0, 0.0, 0.0, 0.0];
+
+    let scores = Decoder::span_scores(&decoder(), 0, 2, 2, 2, 0, 1, &logits, &[]);
+
+    assert_eq!(scores.len(), 2);
+    assert!(scores[0] > 0.85 && scores[1] < 0.15);
+}
+
+#[test]
+fn optimal_keeps_two_adjacent_spans_over_one_covering_both() {
+    let mut spans = vec![
+        span(0, 20, 0, 0.9),
+        span(0, 8, 1, 0.8),
+        span(10, 20, 1, 0.85),
+    ];
+
+    let greedy = decoder().select_with(&mut spans, true, false, SelectionStrategy::Greedy);
+    assert_eq!(greedy, vec![span(0, 20, 0, 0.9)]);
+
+    for strategy in [
+        SelectionStrategy::MaxTotalScore,
+        SelectionStrategy::MaxLogOdds,
+    ] {
+        let selected = decoder().select_with(&mut spans, true, false, strategy);
+        assert_eq!(selected, vec![span(0, 8, 1, 0.8), span(10, 20, 1, 0.85)]);
+    }
+}
+
+#[test]
+fn optimal_matches_greedy_when_the_best_span_is_also_the_best_set() {
+    for flat_ner in [true, false] {
+        for multi_label in [true, false] {
+            let spans = vec![
+                span(0, 15, 0, 0.9),
+                span(8, 15, 1, 0.8),
+                span(10, 20, 2, 0.7),
+                span(30, 35, 0, 0.6),
+            ];
+
+            let greedy = decoder().select_with(
+                &mut spans.clone(),
+                flat_ner,
+                multi_label,
+                SelectionStrategy::Greedy,
+            );
+            let optimal = decoder().select_with(
+                &mut spans.clone(),
+                flat_ner,
+                multi_label,
+                SelectionStrategy::MaxTotalScore,
+            );
+
+            let sorted = |mut spans: Vec<SpanCandidate>| {
+                spans.sort_by_key(|span| (span.start, span.end, span.class_id));
+                spans
+            };
+            assert_eq!(sorted(greedy), sorted(optimal));
+        }
+    }
+}
+
+#[test]
+fn pool_labels_keeps_the_best_prompt_of_each_label() {
+    // Prompts 0 and 1 stand for label 0, prompt 2 for label 1.
+    let canonical = [0, 0, 1];
+    let mut best = span(0, 10, 1, 0.8);
+    best.label_scores = vec![0.6, 0.8, 0.3];
+    let mut spans = vec![
+        span(0, 10, 0, 0.6),
+        best,
+        span(0, 10, 2, 0.55),
+        span(12, 20, 1, 0.7),
+    ];
+
+    pool_labels(&mut spans, &canonical, 2);
+
+    let mut pooled = span(0, 10, 0, 0.8);
+    pooled.label_scores = vec![0.8, 0.3];
+    assert_eq!(
+        spans,
+        vec![pooled, span(0, 10, 1, 0.55), span(12, 20, 0, 0.7)]
+    );
+}
+
+#[test]
+fn decode_candidates_skips_padded_classes() {
+    // A row with one label in a batch padded to two classes: the padding cell
+    // scores high but has no threshold.
+    let logits = [2.0, 5.0];
+
+    let spans = Decoder::decode_candidates(
+        &decoder(),
+        1,
+        1,
+        1,
+        2,
+        &[vec![0]],
+        &[vec![5]],
+        &logits,
+        &[0.5],
+        &[],
+    );
+
+    assert_eq!(spans[0].len(), 1);
+    assert_eq!(spans[0][0].class_id, 0);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::tokenizer;
+use glinerrust::config::ModelConfig;
+use glinerrust::decoder::{BaseDecoder, Decoder, SpanDecoder, TokenDecoder};
+use glinerrust::processor::{pad_array, pad_array_3d, Processor, SpanProcessor};
+
+fn texts(texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|text| text.to_string()).collect()
+}
+
+#[test]
+fn texts_without_words_encode_to_the_prompt_alone() {
+    let processor = Processor::new(ModelConfig::default(), tokenizer()).unwrap();
+
+    for batch in [
+        texts(&[]),
+        texts(&["", "  \n "]),
+        texts(&["", "alice met bob", " "]),
+    ] {
+        let (batch_tokens, _, _) = processor.batch_tokenize_text(&batch);
+        let (input_texts, text_lengths, prompt_lengths) =
+            processor.prepare_text_inputs(&batch_tokens, &["person"]);
+        let expected: Vec<usize> = batch
+            .iter()
+            .map(|text| text.split_whitespace().count())
+            .collect();
+        assert_eq!(text_lengths, expected);
+
+        let (inputs_ids, attention_masks, words_masks) = processor
+            .encode_inputs(&input_texts, Some(&prompt_lengths))
+            .unwrap();
+        assert_eq!(inputs_ids.len(), batch.len());
+        for ((ids, mask), words) in inputs_ids.iter().zip(&attention_masks).zip(&words_masks) {
+            assert_eq!(ids.len(), mask.len());
+            // [CLS] <<ENT>> person <<SEP>> ... [SEP]
+            assert_eq!(
+                ids.len(),
+                5 + words.iter().filter(|&&word| word > 0).count()
+            );
+        }
+        assert_eq!(pad_array(&inputs_ids).len(), batch.len());
+    }
+}
+
+#[test]
+fn texts_without_words_and_empty_batches_have_no_spans() {
+    let processor = SpanProcessor::new(ModelConfig::default(), tokenizer()).unwrap();
+
+    let batch_tokens = vec![vec![], vec!["alice".to_string()], vec![]];
+    let (span_idxs, span_masks) = processor.prepare_spans(&batch_tokens, 12);
+    assert!(span_idxs[0].is_empty() && span_masks[0].is_empty());
+    assert_eq!(span_idxs[1].len(), 12);
+    let padded = pad_array_3d(&span_idxs);
+    assert!(padded.iter().all(|row| row.len() == 12));
+    assert!(padded.iter().flatten().all(|pair| pair.len() == 2));
+
+    let (span_idxs, span_masks) = processor.prepare_spans(&[], 12);
+    assert!(span_idxs.is_empty() && span_masks.is_empty());
+    assert!(pad_array_3d(&span_idxs).is_empty());
+}
+
+#[test]
+fn prompts_without_labels_hold_the_separator_alone() {
+    let processor = Processor::new(ModelConfig::default(), tokenizer()).unwrap();
+    let (input_texts, _, prompt_lengths) =
+        processor.prepare_text_inputs(&[vec!["alice".to_string()]], &[]);
+    assert_eq!(
+        input_texts,
+        vec![vec!["<<SEP>>".to_string(), "alice".to_string()]]
+    );
+    assert_eq!(prompt_lengths, vec![1]);
+}
+
+#[test]
+fn decoding_empty_outputs_finds_nothing() {
+    let span = SpanDecoder::new();
+    let token = TokenDecoder::new();
+
+    for decoder in [&span as &dyn Decoder, &token as &dyn Decoder] {
+        let candidates = decoder.decode_candidates(
+            2,
+            0,
+            12,
+            1,
+            &[vec![], vec![]],
+            &[vec![], vec![]],
+            &[],
+            &[0.5],
+            &[],
+        );
+        assert_eq!(candidates, vec![vec![], vec![]]);
+
+        let candidates = decoder.decode_candidates(0, 0, 12, 1, &[], &[], &[], &[0.5], &[]);
+        assert!(candidates.is_empty());
+    }
+}
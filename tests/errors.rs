@@ -0,0 +1,58 @@
+use glinerrust::gliner::InitConfig;
+use glinerrust::processor::RegexTokenSplitter;
+use glinerrust::recognizers::PatternRecognizer;
+use glinerrust::{Gliner, GlinerError, InferenceOptions};
+
+fn config(tokenizer_path: &str) -> InitConfig {
+    InitConfig {
+        tokenizer_path: tokenizer_path.to_string(),
+        model_path: "missing/model.onnx".to_string(),
+        num_threads: Some(1),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn missing_files_are_reported_instead_of_panicking() {
+    let mut gliner = Gliner::new(config("missing/tokenizer.json"));
+    match gliner.initialize().await {
+        Err(GlinerError::FileError { action, path, .. }) => {
+            assert_eq!(action, "loading");
+            assert!(path.ends_with("tokenizer.json"));
+        }
+        other => panic!("expected a file error, got {:?}", other.map(|_| ())),
+    }
+
+    assert!(matches!(
+        Gliner::from_dir("missing", Some(1)).await,
+        Err(GlinerError::FileError { .. })
+    ));
+    assert!(matches!(
+        InferenceOptions::from_file("missing/options.json"),
+        Err(GlinerError::FileError {
+            action: "reading",
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn inference_before_initialize_is_an_error() {
+    let gliner = Gliner::new(config("tokenizer.json"));
+    let result = gliner
+        .inference(&["Some text".to_string()], &["person"], true, 0.5)
+        .await;
+    assert!(matches!(result, Err(GlinerError::InferenceError(_))));
+}
+
+#[test]
+fn invalid_patterns_are_initialization_errors() {
+    assert!(matches!(
+        RegexTokenSplitter::new("("),
+        Err(GlinerError::InitializationError(_))
+    ));
+    assert!(matches!(
+        PatternRecognizer::new("id", "[a-"),
+        Err(GlinerError::InitializationError(_))
+    ));
+}
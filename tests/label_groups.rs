@@ -0,0 +1,57 @@
+mod common;
+
+use common::tokenizer;
+use glinerrust::config::ModelConfig;
+use glinerrust::decoder::{remap_group, BaseDecoder, Decoder, SpanDecoder};
+use glinerrust::processor::Processor;
+
+// Every label is one subword, so a prompt costs two tokens per label plus
+// <<SEP>>, [CLS] and [SEP].
+const LABELS: [&str; 5] = ["alice", "met", "bob", "person", "city"];
+
+#[test]
+fn labels_are_grouped_within_half_of_max_len() {
+    let processor = Processor::new(ModelConfig::default(), tokenizer()).unwrap();
+    assert_eq!(processor.prompt_token_count(&LABELS), 13);
+
+    // The whole list fits in half of 26.
+    assert_eq!(processor.label_groups(&LABELS, 26), vec![0..5]);
+    // 3 + 2 + 2 fills a 7 token prompt.
+    assert_eq!(processor.label_groups(&LABELS, 14), vec![0..2, 2..4, 4..5]);
+    assert_eq!(
+        processor.partition_labels(&LABELS, 7),
+        vec![0..2, 2..4, 4..5]
+    );
+    // A label that does not fit on its own still gets a group.
+    assert_eq!(
+        processor.partition_labels(&LABELS[..3], 4),
+        vec![0..1, 1..2, 2..3]
+    );
+    assert!(processor.partition_labels(&[], 4).is_empty());
+}
+
+#[test]
+fn class_ids_of_a_group_map_to_the_whole_label_list() {
+    // Output of the second group (labels 2 and 3) for a one word text.
+    let decoder = SpanDecoder::new();
+    let group = 2..4;
+    let thresholds = [0.5, 0.5, 0.5, 0.99];
+    let spans = decoder.decode_candidates(
+        1,
+        1,
+        1,
+        group.len(),
+        &[vec![0]],
+        &[vec![5]],
+        &[3.0, 3.0],
+        &thresholds[group.clone()],
+        &[],
+    );
+
+    let class_ids: Vec<usize> = remap_group(spans.into_iter().flatten(), &group)
+        .iter()
+        .map(|span| span.class_id)
+        .collect();
+    // sigmoid(3.0) passes 0.5 but not the 0.99 of label 3.
+    assert_eq!(class_ids, vec![2]);
+}
@@ -0,0 +1,37 @@
+use glinerrust::labels::{LabelDefinition, LabelSet};
+use glinerrust::{GlinerError, InferenceOptions};
+
+#[test]
+fn expands_labels_into_description_and_aliases() {
+    let options = InferenceOptions::default().with_label_definition(
+        "PERSON",
+        LabelDefinition::new("person name").with_alias("human"),
+    );
+    let labels = LabelSet::new(&["PERSON", "city"], &options.labels).unwrap();
+
+    assert_eq!(labels.names(), vec!["PERSON", "city"]);
+    assert_eq!(labels.prompts(), vec!["person name", "human", "city"]);
+    assert_eq!(labels.prompt_names(), vec!["PERSON", "PERSON", "city"]);
+    assert_eq!(labels.canonical(), &[0, 0, 1]);
+    assert!(!labels.is_plain());
+    assert!(LabelSet::new(&["PERSON", "city"], &Default::default())
+        .unwrap()
+        .is_plain());
+}
+
+#[test]
+fn label_definitions_load_from_profiles() {
+    let options: InferenceOptions =
+        serde_json::from_str(r#"{"labels": {"ORG": {"aliases": ["company", "organization"]}}}"#)
+            .unwrap();
+    let labels = LabelSet::new(&["ORG"], &options.labels).unwrap();
+    assert_eq!(labels.prompts(), vec!["ORG", "company", "organization"]);
+}
+
+#[test]
+fn label_sets_need_a_label() {
+    assert!(matches!(
+        LabelSet::new(&[], &Default::default()),
+        Err(GlinerError::NoLabels)
+    ));
+}
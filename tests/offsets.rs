@@ -0,0 +1,144 @@
+mod common;
+
+use common::tokenizer;
+use glinerrust::config::ModelConfig;
+use glinerrust::offsets::{set_offsets, set_word_offsets, OffsetUnit};
+use glinerrust::processor::{join_words, Processor, WhitespaceTokenSplitter};
+use glinerrust::EntityResult;
+
+const TEXT: &str = "Zoë met 👋 Bob.";
+
+fn entities(spans: &[(usize, usize)]) -> Vec<EntityResult> {
+    spans
+        .iter()
+        .map(|&(start, end)| EntityResult {
+            span_text: TEXT[start..end].to_string(),
+            start,
+            end,
+            label: "person".to_string(),
+            score: 0.9,
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn annotate(spans: &[(usize, usize)], unit: OffsetUnit) -> Vec<EntityResult> {
+    let (starts, ends): (Vec<usize>, Vec<usize>) = WhitespaceTokenSplitter::new()
+        .call(TEXT)
+        .map(|(_, start, end)| (start, end))
+        .unzip();
+    let mut entities = entities(spans);
+    set_offsets(TEXT, &starts, &ends, &mut entities, unit);
+    entities
+}
+
+#[test]
+fn converts_byte_offsets_to_code_points_and_utf16_units() {
+    // "Bob" starts at byte 14, code point 10 and UTF-16 unit 11.
+    assert_eq!(
+        OffsetUnit::Chars.convert_sorted(TEXT, &[0, 4, 14, 17, 18]),
+        vec![0, 3, 10, 13, 14]
+    );
+    assert_eq!(
+        OffsetUnit::Utf16.convert_sorted(TEXT, &[0, 4, 14, 17, 18]),
+        vec![0, 3, 11, 14, 15]
+    );
+
+    let chars = annotate(&[(14, 17), (0, 4)], OffsetUnit::Chars);
+    assert_eq!(
+        chars
+            .iter()
+            .map(|e| (e.char_start, e.char_end, e.utf16_start))
+            .collect::<Vec<_>>(),
+        vec![(Some(10), Some(13), None), (Some(0), Some(3), None)]
+    );
+
+    let utf16 = annotate(&[(14, 17)], OffsetUnit::Utf16);
+    assert_eq!(
+        (utf16[0].utf16_start, utf16[0].utf16_end),
+        (Some(11), Some(14))
+    );
+    assert_eq!(utf16[0].char_start, None);
+}
+
+#[test]
+fn word_indices_cover_every_overlapped_word() {
+    let entities = annotate(&[(0, 4), (14, 17), (1, 6)], OffsetUnit::Bytes);
+    let words: Vec<(usize, usize)> = entities
+        .iter()
+        .map(|entity| (entity.word_start, entity.word_end))
+        .collect();
+    assert_eq!(words, vec![(0, 1), (3, 4), (0, 2)]);
+    assert!(entities.iter().all(|entity| entity.char_start.is_none()));
+}
+
+#[test]
+fn pretokenized_entities_take_the_offsets_of_their_words() {
+    // "New York's" split as "New", "York", "'s", with character offsets.
+    let words: Vec<String> = ["New", "York", "'s"].map(String::from).to_vec();
+    let offsets = [(0, 3), (4, 8), (8, 10)];
+    let (text, (tokens, starts, ends)) = join_words(&words);
+    assert_eq!(text, "New York 's");
+    assert_eq!(tokens, words);
+    assert_eq!(
+        (starts.clone(), ends.clone()),
+        (vec![0, 4, 9], vec![3, 8, 11])
+    );
+
+    let mut entities = vec![EntityResult {
+        span_text: text[0..8].to_string(),
+        start: 0,
+        end: 8,
+        label: "location".to_string(),
+        score: 0.9,
+        ..Default::default()
+    }];
+    set_offsets(&text, &starts, &ends, &mut entities, OffsetUnit::Chars);
+    set_word_offsets(&mut entities, &offsets);
+    let entity = &entities[0];
+    assert_eq!((entity.word_start, entity.word_end), (0, 2));
+    assert_eq!((entity.start, entity.end), (0, 8));
+    assert_eq!(entity.char_start, None);
+
+    let mut trailing = vec![EntityResult {
+        start: 4,
+        end: 11,
+        word_start: 1,
+        word_end: 3,
+        ..Default::default()
+    }];
+    set_word_offsets(&mut trailing, &offsets);
+    assert_eq!((trailing[0].start, trailing[0].end), (4, 10));
+}
+
+#[test]
+fn pretokenized_whitespace_words_do_not_shift_later_words() {
+    let processor = Processor::new(ModelConfig::default(), tokenizer()).unwrap();
+    let words: Vec<String> = ["alice", "\n\n", "bob"].map(String::from).to_vec();
+    let (text, text_words) = join_words(&words);
+    assert_eq!(text, "alice \n\n bob");
+
+    // The line break has no subword and is left out of the model pass.
+    let (tokens, starts, ends) = processor.drop_empty_words(&text_words);
+    assert_eq!(tokens, vec!["alice", "bob"]);
+    assert_eq!((starts, ends), (vec![0, 9], vec![5, 12]));
+
+    // Every word counted in `text_lengths` gets a `words_mask` index.
+    let (input_tokens, text_lengths, prompt_lengths) =
+        processor.prepare_row_text_inputs(&[tokens], &[&["person"]]);
+    let (_, _, words_masks) = processor
+        .encode_inputs(&input_tokens, Some(&prompt_lengths))
+        .unwrap();
+    assert_eq!(text_lengths, vec![2]);
+    assert_eq!(words_masks[0].iter().max(), Some(&2));
+
+    // "bob" still maps to its original word index.
+    let mut entities = vec![EntityResult {
+        start: 9,
+        end: 12,
+        ..Default::default()
+    }];
+    let (_, starts, ends) = &text_words;
+    set_offsets(&text, starts, ends, &mut entities, OffsetUnit::Bytes);
+    assert_eq!((entities[0].word_start, entities[0].word_end), (2, 3));
+}
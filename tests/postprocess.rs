@@ -0,0 +1,125 @@
+use glinerrust::postprocess::{PostProcessing, ENGLISH_STOPWORDS};
+use glinerrust::EntityResult;
+use std::collections::HashMap;
+
+fn entity(text: &str, start: usize, end: usize, label: &str, score: f32) -> EntityResult {
+    EntityResult {
+        span_text: text[start..end].to_string(),
+        start,
+        end,
+        label: label.to_string(),
+        score,
+        ..Default::default()
+    }
+}
+
+fn spans(entities: &[EntityResult]) -> Vec<(&str, usize, usize, &str)> {
+    entities
+        .iter()
+        .map(|e| (e.span_text.as_str(), e.start, e.end, e.label.as_str()))
+        .collect()
+}
+
+#[test]
+fn trims_punctuation_and_stopwords_from_both_ends() {
+    let text = "Ronaldo joined (Al Nassr and) \"U.S.\" fans.";
+    let post_processing = PostProcessing {
+        trim_punctuation: true,
+        keep_punctuation: ".".to_string(),
+        stopwords: ENGLISH_STOPWORDS.iter().map(|s| s.to_string()).collect(),
+        ..Default::default()
+    };
+
+    let entities = post_processing.apply(
+        text,
+        vec![
+            entity(text, 15, 29, "organization", 0.9),
+            entity(text, 29, 36, "location", 0.8),
+            entity(text, 25, 28, "organization", 0.7),
+        ],
+    );
+
+    assert_eq!(
+        spans(&entities),
+        vec![
+            ("Al Nassr", 16, 24, "organization"),
+            ("U.S.", 31, 35, "location"),
+        ]
+    );
+}
+
+#[test]
+fn merges_adjacent_and_hyphenated_spans_with_the_same_label() {
+    let text = "New York and Baden-Baden host Coca Cola";
+    let post_processing = PostProcessing {
+        merge_adjacent: true,
+        ..Default::default()
+    };
+
+    let entities = post_processing.apply(
+        text,
+        vec![
+            entity(text, 4, 8, "location", 0.7),
+            entity(text, 0, 3, "location", 0.6),
+            entity(text, 13, 18, "location", 0.8),
+            entity(text, 19, 24, "location", 0.9),
+            entity(text, 30, 34, "organization", 0.9),
+            entity(text, 35, 39, "person", 0.5),
+        ],
+    );
+
+    assert_eq!(
+        spans(&entities),
+        vec![
+            ("New York", 0, 8, "location"),
+            ("Baden-Baden", 13, 24, "location"),
+            ("Coca", 30, 34, "organization"),
+            ("Cola", 35, 39, "person"),
+        ]
+    );
+    assert_eq!(entities[0].score, 0.6);
+}
+
+#[test]
+fn drops_spans_outside_the_length_bounds_of_their_label() {
+    let text = "Dr X met Maximilian at IBM";
+    let post_processing = PostProcessing {
+        min_chars: HashMap::from([("person".to_string(), 2)]),
+        max_chars: HashMap::from([("organization".to_string(), 2)]),
+        ..Default::default()
+    };
+
+    let entities = post_processing.apply(
+        text,
+        vec![
+            entity(text, 3, 4, "person", 0.9),
+            entity(text, 9, 19, "person", 0.9),
+            entity(text, 23, 26, "organization", 0.9),
+        ],
+    );
+
+    assert_eq!(spans(&entities), vec![("Maximilian", 9, 19, "person")]);
+}
+
+#[test]
+fn entities_with_invalid_offsets_are_left_untouched() {
+    let text = "Café (Paris)";
+    let mut broken = entity(text, 0, 5, "place", 0.9);
+    // Inside the two bytes of "é", then past the end of the text.
+    broken.end = 4;
+    let mut outside = entity(text, 7, 12, "city", 0.8);
+    outside.start = 40;
+    outside.end = 45;
+    let post_processing = PostProcessing {
+        trim_punctuation: true,
+        merge_adjacent: true,
+        ..Default::default()
+    };
+
+    let processed = post_processing.apply(text, vec![broken, outside]);
+
+    assert_eq!(
+        spans(&processed),
+        vec![("Café", 0, 4, "place"), ("Paris", 40, 45, "city")]
+    );
+}
@@ -0,0 +1,64 @@
+use glinerrust::pseudonymize::PseudonymSession;
+use glinerrust::{EntityResult, InferenceResultSingle};
+
+fn result(text: &str, spans: &[(usize, usize, &str)]) -> InferenceResultSingle {
+    InferenceResultSingle {
+        entities: spans
+            .iter()
+            .map(|&(start, end, label)| EntityResult {
+                span_text: text[start..end].to_string(),
+                start,
+                end,
+                label: label.to_string(),
+                score: 0.9,
+                ..Default::default()
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn assigns_stable_placeholders_across_messages() {
+    let mut session = PseudonymSession::new();
+
+    let text = "John Smith met Jane Doe at Acme.";
+    let first = session.pseudonymize(
+        text,
+        &result(
+            text,
+            &[
+                (0, 10, "person"),
+                (15, 23, "person"),
+                (27, 31, "organization"),
+            ],
+        ),
+    );
+    assert_eq!(first.text, "PERSON_1 met PERSON_2 at ORGANIZATION_1.");
+
+    let text = "Did Jane Doe call John Smith?";
+    let second = session.pseudonymize(
+        text,
+        &result(text, &[(4, 12, "person"), (18, 28, "person")]),
+    );
+    assert_eq!(second.text, "Did PERSON_2 call PERSON_1?");
+    assert_eq!(session.pseudonyms().len(), 3);
+}
+
+#[test]
+fn rehydrates_placeholders_despite_case_and_punctuation() {
+    let mut session = PseudonymSession::new();
+    session.placeholder("person", "John Smith");
+    session.placeholder("email address", "jo@example.com");
+    for _ in 0..9 {
+        session.placeholder("person", &format!("someone {}", session.pseudonyms().len()));
+    }
+
+    let json = serde_json::to_string(&session).unwrap();
+    let session: PseudonymSession = serde_json::from_str(&json).unwrap();
+
+    let answer = "Person 1 (email-address_1) wrote to **person_10**; PERSON_99 is unknown.";
+    assert_eq!(
+        session.rehydrate(answer),
+        "John Smith (jo@example.com) wrote to **someone 10**; PERSON_99 is unknown."
+    );
+}
@@ -0,0 +1,96 @@
+use glinerrust::recognizers::{
+    iban_valid, is_date, luhn_valid, GazetteerRecognizer, PatternRecognizer, Recognizer,
+    RecognizerRegistry,
+};
+
+#[test]
+fn validates_checksums() {
+    assert!(luhn_valid("4111 1111 1111 1111"));
+    assert!(!luhn_valid("4111 1111 1111 1112"));
+    assert!(iban_valid("GB82 WEST 1234 5698 7654 32"));
+    assert!(!iban_valid("GB82 WEST 1234 5698 7654 33"));
+    assert!(!iban_valid("GB82"));
+}
+
+#[test]
+fn pattern_matches_pass_their_validator() {
+    let text = "Pay GB82 WEST 1234 5698 7654 32, not GB82 WEST 1234 5698 7654 33.";
+    let ibans = PatternRecognizer::iban("iban").recognize(text);
+    assert_eq!(ibans, vec![(4, 31, 1.0)]);
+
+    let text = "Card 4111-1111-1111-1111 or 4111-1111-1111-1112, mail jo@example.com";
+    let registry = RecognizerRegistry::new()
+        .with(PatternRecognizer::credit_card("card"))
+        .with(PatternRecognizer::email("email").with_score(0.9));
+    let found: Vec<(&str, &str, f32)> = registry
+        .recognize(text)
+        .into_iter()
+        .map(|(start, end, label, score)| (&text[start..end], label, score))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("4111-1111-1111-1111", "card", 1.0),
+            ("jo@example.com", "email", 0.9),
+        ]
+    );
+}
+
+#[test]
+fn ibans_stop_before_following_words() {
+    let recognizer = PatternRecognizer::iban("iban");
+    let matched = |text: &'static str| -> Vec<&'static str> {
+        recognizer
+            .recognize(text)
+            .into_iter()
+            .map(|(start, end, _)| &text[start..end])
+            .collect()
+    };
+    assert_eq!(
+        matched("IBAN DE89370400440532013000 BIC COBADEFF"),
+        vec!["DE89370400440532013000"]
+    );
+    assert_eq!(
+        matched("to DE89 3704 0044 0532 0130 00 AND MORE"),
+        vec!["DE89 3704 0044 0532 0130 00"]
+    );
+    assert_eq!(
+        matched("to DE89 3704 0044 0532 0130 00 MORE"),
+        vec!["DE89 3704 0044 0532 0130 00"]
+    );
+}
+
+#[test]
+fn phones_skip_dates_and_word_fragments() {
+    assert!(is_date("2024-01-15"));
+    assert!(is_date("15/01/2024"));
+    assert!(!is_date("555-12-34"));
+
+    let recognizer = PatternRecognizer::phone("phone");
+    assert!(recognizer.recognize("Filed on 2024-01-15.").is_empty());
+    assert!(recognizer.recognize("Filed on 15.01.2024.").is_empty());
+    assert!(recognizer.recognize("ref AB12345678").is_empty());
+
+    let text = "Call +44 (20) 7946 0958 before 2024-01-15.";
+    let found: Vec<&str> = recognizer
+        .recognize(text)
+        .into_iter()
+        .map(|(start, end, _)| &text[start..end])
+        .collect();
+    assert_eq!(found, vec!["+44 (20) 7946 0958"]);
+}
+
+#[test]
+fn gazetteer_matches_whole_words() {
+    let text = "ACME Corp bought Globexia and globex.";
+    let gazetteer = GazetteerRecognizer::new("customer", &["Acme Corp", "Globex"], true).unwrap();
+    let found: Vec<&str> = gazetteer
+        .recognize(text)
+        .into_iter()
+        .map(|(start, end, _)| &text[start..end])
+        .collect();
+    assert_eq!(found, vec!["ACME Corp", "globex"]);
+
+    let gazetteer = GazetteerRecognizer::new("customer", &["Acme Corp"], false).unwrap();
+    assert!(gazetteer.recognize(text).is_empty());
+}
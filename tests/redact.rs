@@ -0,0 +1,94 @@
+use glinerrust::redact::{redact, RedactionOperator, RedactionPolicy};
+use glinerrust::{EntityResult, InferenceResultSingle};
+
+fn result(text: &str, spans: &[(usize, usize, &str)]) -> InferenceResultSingle {
+    InferenceResultSingle {
+        entities: spans
+            .iter()
+            .map(|&(start, end, label)| EntityResult {
+                span_text: text.get(start..end).unwrap_or_default().to_string(),
+                start,
+                end,
+                label: label.to_string(),
+                score: 0.9,
+                ..Default::default()
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn redacts_nested_spans_once_and_maps_offsets_back() {
+    let text = "Call John Smith at 555-1234 today.";
+    // "John" is nested in "John Smith"; the last entity has invalid offsets.
+    let result = result(
+        text,
+        &[
+            (5, 9, "first name"),
+            (5, 15, "person"),
+            (19, 27, "phone"),
+            (30, 99, "x"),
+        ],
+    );
+    let policy = RedactionPolicy::default().with_label("phone", RedactionOperator::mask());
+
+    let redaction = redact(text, &result, &policy);
+    assert_eq!(redaction.text, "Call <PERSON> at ******** today.");
+    assert_eq!(redaction.spans.len(), 2);
+    assert_eq!(redaction.original_range(5, 13), (5, 15));
+    assert_eq!(redaction.original_offset(7), 5);
+    assert_eq!(redaction.original_offset(2), 2);
+    assert_eq!(&text[redaction.original_offset(26)..], "today.");
+}
+
+#[test]
+fn hash_and_fake_are_keyed_and_deterministic() {
+    let text = "AB-12 cd and AB-12 cd";
+    let result = result(text, &[(0, 8, "id"), (13, 21, "id")]);
+
+    let policy = RedactionPolicy::new(RedactionOperator::Fake {
+        key: "secret".to_string(),
+    });
+    let redaction = redact(text, &result, &policy);
+    let (first, second) = redaction.text.split_once(" and ").unwrap();
+    assert_eq!(first, second);
+    assert_ne!(first, "AB-12 cd");
+    let shape = |s: &str| -> String {
+        s.chars()
+            .map(|c| match c {
+                '0'..='9' => '9',
+                'a'..='z' => 'a',
+                'A'..='Z' => 'A',
+                c => c,
+            })
+            .collect()
+    };
+    assert_eq!(shape(first), "AA-99 aa");
+
+    let hash = |key: &str| {
+        let policy = RedactionPolicy::new(RedactionOperator::Hash {
+            key: key.to_string(),
+            length: 8,
+        });
+        redact(text, &result, &policy).text
+    };
+    let hashed = hash("secret");
+    let (first, second) = hashed.split_once(" and ").unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.len(), 8);
+    assert_ne!(hashed, hash("other"));
+}
+
+#[test]
+fn dropped_spans_leave_empty_regions() {
+    let text = "Email jo@example.com now";
+    let result = result(text, &[(6, 20, "email")]);
+    let redaction = redact(
+        text,
+        &result,
+        &RedactionPolicy::new(RedactionOperator::Drop),
+    );
+    assert_eq!(redaction.text, "Email  now");
+    assert_eq!(redaction.original_range(6, 6), (6, 20));
+    assert_eq!(redaction.original_offset(7), 21);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::tokenizer;
+use glinerrust::config::ModelConfig;
+use glinerrust::processor::{pad_array, Processor};
+use glinerrust::{InferenceItem, InferenceOptions};
+
+#[test]
+fn each_row_is_prompted_with_its_own_labels() {
+    let processor = Processor::new(ModelConfig::default(), tokenizer()).unwrap();
+    let (batch_tokens, _, _) =
+        processor.batch_tokenize_text(&["alice met bob".to_string(), "bob".to_string()]);
+    let entities: [&[&str]; 2] = [&["person"], &["person", "city"]];
+
+    let (input_texts, text_lengths, prompt_lengths) =
+        processor.prepare_row_text_inputs(&batch_tokens, &entities);
+    assert_eq!(text_lengths, vec![3, 1]);
+    assert_eq!(prompt_lengths, vec![3, 5]);
+    assert_eq!(
+        input_texts[1],
+        vec!["<<ENT>>", "person", "<<ENT>>", "city", "<<SEP>>", "bob"]
+    );
+
+    let (inputs_ids, attention_masks, words_masks) = processor
+        .encode_inputs(&input_texts, Some(&prompt_lengths))
+        .unwrap();
+    // Both rows hold 6 prompt and text words plus [CLS] and [SEP], but only
+    // the words after each prompt are numbered.
+    assert_eq!(words_masks[0], vec![0, 0, 0, 0, 1, 2, 3, 0]);
+    assert_eq!(words_masks[1], vec![0, 0, 0, 0, 0, 0, 1, 0]);
+    let padded = pad_array(&attention_masks);
+    assert!(padded.iter().all(|row| row.len() == inputs_ids[0].len()));
+}
+
+#[test]
+fn uniform_prompts_match_per_row_prompts() {
+    let processor = Processor::new(ModelConfig::default(), tokenizer()).unwrap();
+    let (batch_tokens, _, _) =
+        processor.batch_tokenize_text(&["alice met bob".to_string(), "bob".to_string()]);
+    let labels: &[&str] = &["person", "city"];
+
+    assert_eq!(
+        processor.prepare_text_inputs(&batch_tokens, labels),
+        processor.prepare_row_text_inputs(&batch_tokens, &[labels, labels])
+    );
+
+    let item = InferenceItem::new("alice met bob", labels, InferenceOptions::new(true, 0.3));
+    assert_eq!(item.labels(), labels);
+    assert_eq!(item.options.threshold, 0.3);
+}
@@ -0,0 +1,78 @@
+use glinerrust::chunking::plan_sentence_windows;
+use glinerrust::processor::{RuleSentenceSplitter, SentenceSplitter};
+
+fn split(text: &str) -> Vec<&str> {
+    RuleSentenceSplitter::new()
+        .split(text)
+        .into_iter()
+        .map(|(start, end)| &text[start..end])
+        .collect()
+}
+
+#[test]
+fn abbreviations_and_initials_do_not_end_sentences() {
+    assert_eq!(
+        split("Dr. Smith met J. R. R. Tolkien at 5 p.m. on Monday. They talked."),
+        vec![
+            "Dr. Smith met J. R. R. Tolkien at 5 p.m. on Monday.",
+            "They talked."
+        ]
+    );
+    assert_eq!(
+        split("The price rose by approx. 5%. Sales fell."),
+        vec!["The price rose by approx. 5%.", "Sales fell."]
+    );
+    let custom = RuleSentenceSplitter::with_abbreviations(&["Abs."]);
+    assert_eq!(custom.split("See Abs. Two. Done.").len(), 2);
+}
+
+#[test]
+fn closing_quotes_stay_with_their_sentence() {
+    assert_eq!(
+        split("He said \"Stop!\" Then he left. (It was late.) \"Why?\" she asked."),
+        vec![
+            "He said \"Stop!\"",
+            "Then he left.",
+            "(It was late.)",
+            "\"Why?\" she asked."
+        ]
+    );
+    // A lowercase word after a terminator does not start a sentence.
+    assert_eq!(split("Is it? yes it is."), vec!["Is it? yes it is."]);
+}
+
+#[test]
+fn blank_lines_and_bullets_start_sentences() {
+    assert_eq!(
+        split("Title without period\n\nFirst paragraph here"),
+        vec!["Title without period", "First paragraph here"]
+    );
+    assert_eq!(
+        split("Items:\n- apples\n- pears\n2. plums\nsingle newline\ncontinues"),
+        vec![
+            "Items:",
+            "- apples",
+            "- pears",
+            "2. plums\nsingle newline\ncontinues"
+        ]
+    );
+}
+
+#[test]
+fn oversize_sentences_are_split_into_cut_windows() {
+    // Sentences of 2, 6 and 2 words with one token each, budget 4.
+    let windows = plan_sentence_windows(&[0..2, 2..8, 8..10], &[1; 10], 4, 1);
+    assert_eq!(
+        windows,
+        vec![
+            (0..2, false, false),
+            (2..6, false, true),
+            (5..8, true, false),
+            (8..10, false, false),
+        ]
+    );
+
+    // Short sentences are packed together, and empty ones are skipped.
+    let windows = plan_sentence_windows(&[0..1, 1..1, 1..3, 3..5], &[1; 5], 4, 1);
+    assert_eq!(windows, vec![(0..3, false, false), (3..5, false, false)]);
+}
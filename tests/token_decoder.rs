@@ -0,0 +1,100 @@
+use glinerrust::decoder::{BaseDecoder, SpanMode, TokenDecoder};
+
+const HIGH: f32 = 3.0;
+const LOW: f32 = -3.0;
+
+// Word `i` of the text spans bytes `4 * i..4 * i + 3`.
+fn words(count: usize) -> (Vec<usize>, Vec<usize>) {
+    (0..count).map(|word| (4 * word, 4 * word + 3)).unzip()
+}
+
+// Output of one text laid out as `[words, classes, 3]` from the start, end
+// and inside logits of every class, given per word.
+fn grid(classes: &[[[f32; 3]; 4]]) -> Vec<f32> {
+    (0..4)
+        .flat_map(|word| classes.iter().flat_map(move |class| class[word]))
+        .collect()
+}
+
+fn offsets(
+    decoder: &TokenDecoder,
+    output: &[f32],
+    num_classes: usize,
+    thresholds: &[f32],
+) -> Vec<(usize, usize, usize)> {
+    let (starts, ends) = words(4);
+    decoder.collect_spans(
+        1,
+        4,
+        num_classes,
+        &[starts],
+        &[ends],
+        output,
+        thresholds,
+        &[],
+    )[0]
+    .iter()
+    .map(|span| (span.start, span.end, span.class_id))
+    .collect()
+}
+
+#[test]
+fn every_end_after_a_start_makes_a_span_until_inside_breaks() {
+    let decoder = TokenDecoder::new();
+    // Starts at word 0, may end at words 1, 2 or 3, but word 3 is not inside.
+    let output = grid(&[[
+        [HIGH, LOW, HIGH],
+        [LOW, HIGH, HIGH],
+        [LOW, HIGH, 1.0],
+        [LOW, HIGH, LOW],
+    ]]);
+
+    assert_eq!(
+        offsets(&decoder, &output, 1, &[0.5]),
+        vec![(0, 7, 0), (0, 11, 0)]
+    );
+    let (starts, ends) = words(4);
+    let spans = decoder.collect_spans(1, 4, 1, &[starts], &[ends], &output, &[0.5], &[]);
+    // The mean of the inside probabilities of the words of the span.
+    let sigmoid = |x: f32| 1.0 / (1.0 + (-x).exp());
+    assert!((spans[0][0].score - sigmoid(HIGH)).abs() < 1e-6);
+    let mean = (2.0 * sigmoid(HIGH) + sigmoid(1.0)) / 3.0;
+    assert!((spans[0][1].score - mean).abs() < 1e-6);
+}
+
+#[test]
+fn span_scores_ignore_start_and_end_beyond_their_thresholds() {
+    let decoder = TokenDecoder::new();
+    // Words 1 to 3 with a barely passing start and end but confident insides.
+    let output = grid(&[[[LOW; 3], [0.1, LOW, 2.0], [LOW, LOW, 4.0], [LOW, 0.1, 2.0]]]);
+    let (starts, ends) = words(4);
+    let spans = decoder.collect_spans(1, 4, 1, &[starts], &[ends], &output, &[0.5], &[]);
+    assert_eq!(spans[0].len(), 1);
+    assert_eq!((spans[0][0].start, spans[0][0].end), (4, 15));
+    // (0.8808 + 0.9820 + 0.8808) / 3.
+    assert!((spans[0][0].score - 0.914_536).abs() < 1e-5);
+}
+
+#[test]
+fn each_class_is_held_to_its_own_threshold() {
+    let decoder = TokenDecoder::new();
+    let word = [1.0, 1.0, 1.0];
+    let output = grid(&[
+        [word, [LOW; 3], [LOW; 3], [LOW; 3]],
+        [word, [LOW; 3], [LOW; 3], [LOW; 3]],
+    ]);
+
+    // sigmoid(1.0) is about 0.73.
+    assert_eq!(offsets(&decoder, &output, 2, &[0.5, 0.9]), vec![(0, 3, 0)]);
+    assert_eq!(offsets(&decoder, &output, 2, &[0.9, 0.5]), vec![(0, 3, 1)]);
+    // Classes without a threshold, such as padded ones, are not read.
+    assert_eq!(offsets(&decoder, &output, 2, &[0.5]), vec![(0, 3, 0)]);
+}
+
+#[test]
+fn span_mode_is_read_from_gliner_configs() {
+    assert_eq!(SpanMode::from_config("token_level"), SpanMode::TokenLevel);
+    assert_eq!(SpanMode::from_config("token-level"), SpanMode::TokenLevel);
+    assert_eq!(SpanMode::from_config("markerV0"), SpanMode::Span);
+    assert_eq!(SpanMode::from_config(""), SpanMode::Span);
+}
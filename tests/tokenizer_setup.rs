@@ -0,0 +1,80 @@
+mod common;
+
+use common::{tokenizer, with_markers, word_level};
+use glinerrust::config::ModelConfig;
+use glinerrust::processor::Processor;
+use glinerrust::GlinerError;
+use tokenizers::processors::roberta::RobertaProcessing;
+
+// Ids `encode_inputs` puts around a one word text.
+fn encoded(processor: &Processor) -> Vec<u32> {
+    let (inputs_ids, _, _) = processor
+        .encode_inputs(&[vec!["alice".to_string()]], None)
+        .unwrap();
+    inputs_ids[0].clone()
+}
+
+fn init_error(result: Result<Processor, GlinerError>) -> String {
+    match result {
+        Err(GlinerError::InitializationError(message)) => message,
+        Err(other) => panic!("unexpected error: {other}"),
+        Ok(_) => panic!("processor was created"),
+    }
+}
+
+#[test]
+fn special_tokens_come_from_the_post_processor() {
+    // [CLS] = 1, alice = 3, [SEP] = 2.
+    let bert = Processor::new(ModelConfig::default(), tokenizer()).unwrap();
+    assert_eq!(encoded(&bert), vec![1, 3, 2]);
+
+    // <s> = 0, alice = 3, </s> = 2.
+    let mut roberta = word_level(&["<s>", "<pad>", "</s>", "alice", "[UNK]"]);
+    roberta.with_post_processor(Some(RobertaProcessing::new(
+        ("</s>".to_string(), 2),
+        ("<s>".to_string(), 0),
+    )));
+    let roberta = Processor::new(ModelConfig::default(), with_markers(roberta)).unwrap();
+    assert_eq!(encoded(&roberta), vec![0, 3, 2]);
+}
+
+#[test]
+fn special_tokens_fall_back_to_well_known_names() {
+    let processor = Processor::new(
+        ModelConfig::default(),
+        with_markers(word_level(&["[UNK]", "[CLS]", "[SEP]", "alice"])),
+    )
+    .unwrap();
+    assert_eq!(encoded(&processor), vec![1, 3, 2]);
+
+    let message = init_error(Processor::new(
+        ModelConfig::default(),
+        with_markers(word_level(&["[UNK]", "alice"])),
+    ));
+    assert!(message.contains("special tokens"));
+}
+
+#[test]
+fn tokenizers_without_gliner_markers_are_rejected() {
+    // No marker tokens at all.
+    let message = init_error(Processor::new(
+        ModelConfig::default(),
+        word_level(&["[UNK]", "[CLS]", "[SEP]", "alice"]),
+    ));
+    assert!(message.contains("<<ENT>>"));
+
+    // Markers in the vocabulary, but split by the pre-tokenizer.
+    let message = init_error(Processor::new(
+        ModelConfig::default(),
+        word_level(&["[UNK]", "[CLS]", "[SEP]", "<<ENT>>", "<<SEP>>"]),
+    ));
+    assert!(message.contains("single added token"));
+
+    // Markers other than the ones the model config names.
+    let config = ModelConfig {
+        sep_token: "[SEP_MARK]".to_string(),
+        ..Default::default()
+    };
+    let message = init_error(Processor::new(config, tokenizer()));
+    assert!(message.contains("[SEP_MARK]"));
+}
@@ -0,0 +1,59 @@
+use glinerrust::{EntityResult, EntityTree};
+
+fn entity(span_text: &str, start: usize, end: usize, label: &str) -> EntityResult {
+    EntityResult {
+        span_text: span_text.to_string(),
+        start,
+        end,
+        label: label.to_string(),
+        score: 0.9,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn nested_entities_become_children_of_the_smallest_container() {
+    // "Bank of America Tower, in Charlotte"
+    let entities = vec![
+        entity("Charlotte", 26, 35, "location"),
+        entity("America", 8, 15, "location"),
+        entity("Bank of America", 0, 15, "organization"),
+        entity("Bank of America Tower", 0, 21, "building"),
+    ];
+
+    let tree = EntityTree::from_entities(&entities);
+
+    let labels: Vec<(&str, usize, Option<usize>)> = tree
+        .nodes
+        .iter()
+        .map(|node| (node.entity.label.as_str(), node.depth, node.parent))
+        .collect();
+    assert_eq!(
+        labels,
+        vec![
+            ("building", 0, None),
+            ("organization", 1, Some(0)),
+            ("location", 2, Some(1)),
+            ("location", 0, None),
+        ]
+    );
+    assert_eq!(tree.roots, vec![0, 3]);
+    assert_eq!(tree.nodes[1].children, vec![2]);
+
+    let json = serde_json::to_value(&tree).unwrap();
+    assert_eq!(json["nodes"][2]["span_text"], "America");
+    assert_eq!(json["nodes"][2]["parent"], 1);
+}
+
+#[test]
+fn entities_with_the_same_offsets_are_siblings() {
+    let entities = vec![
+        entity("Paris", 0, 5, "location"),
+        entity("Paris", 0, 5, "organization"),
+    ];
+
+    let tree = EntityTree::from_entities(&entities);
+
+    assert_eq!(tree.roots, vec![0, 1]);
+    assert!(tree.nodes.iter().all(|node| node.depth == 0));
+}
@@ -0,0 +1,69 @@
+use glinerrust::config::ModelConfig;
+use glinerrust::processor::{words_splitter_from_config, CjkTokenSplitter, WordSplitter};
+use glinerrust::GlinerError;
+
+fn words<'a>(splitter: &dyn WordSplitter, text: &'a str) -> Vec<&'a str> {
+    splitter
+        .split(text)
+        .into_iter()
+        .map(|(word, start, end)| {
+            assert_eq!(word, &text[start..end]);
+            &text[start..end]
+        })
+        .collect()
+}
+
+fn config(words_splitter_type: &str, pattern: Option<&str>) -> ModelConfig {
+    ModelConfig {
+        words_splitter_type: words_splitter_type.to_string(),
+        words_splitter_pattern: pattern.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn cjk_characters_are_words_of_their_own() {
+    let splitter = CjkTokenSplitter::new();
+    // Latin runs inside unspaced text stay whole.
+    assert_eq!(
+        words(&splitter, "我在Apple工作。iPhone 15 发布"),
+        vec!["我", "在", "Apple", "工", "作", "。", "iPhone", "15", "发", "布"]
+    );
+    // Hiragana, katakana and halfwidth katakana.
+    assert_eq!(
+        words(&splitter, "東京タワーへｶﾅ"),
+        vec!["東", "京", "タ", "ワ", "ー", "へ", "ｶ", "ﾅ"]
+    );
+    assert_eq!(
+        words(&splitter, "plain text, only"),
+        vec!["plain", "text", ",", "only"]
+    );
+}
+
+#[test]
+fn splitters_are_built_from_the_model_config() {
+    for name in ["cjk", "char", "jieba", "mecab", "janome"] {
+        let splitter = words_splitter_from_config(&config(name, None)).unwrap();
+        assert_eq!(words(splitter.as_ref(), "北京 hi"), vec!["北", "京", "hi"]);
+    }
+
+    let whitespace = words_splitter_from_config(&config("whitespace", None)).unwrap();
+    assert_eq!(words(whitespace.as_ref(), "北京 hi"), vec!["北京", "hi"]);
+    // Unknown types fall back to whitespace splitting.
+    let unknown = words_splitter_from_config(&config("stanza", None)).unwrap();
+    assert_eq!(words(unknown.as_ref(), "北京 hi"), vec!["北京", "hi"]);
+
+    let regex = words_splitter_from_config(&config("regex", Some(r"[^,]+"))).unwrap();
+    assert_eq!(words(regex.as_ref(), "a b,c"), vec!["a b", "c"]);
+}
+
+#[test]
+fn regex_splitters_need_a_valid_pattern() {
+    for pattern in [None, Some("(")] {
+        match words_splitter_from_config(&config("regex", pattern)) {
+            Err(GlinerError::InitializationError(_)) => {}
+            Err(other) => panic!("unexpected error: {other}"),
+            Ok(_) => panic!("splitter was built"),
+        }
+    }
+}